@@ -1,26 +1,101 @@
-mod sim8086;
-use sim8086::{dis::Dissassembler, emu::Emulator};
-use std::{
-    env::args,
-    fs::File,
-    io::{Error, Read},
+use computer_enhance_8086::sim8086::{
+    dis::Dissassembler,
+    dos::Dos,
+    emu::{Emulator, Termination},
+    load::{load, LOAD_SEGMENT},
+    opc::mov::Register,
 };
+use std::{env::args, fs, path::Path, process::exit};
 
-fn main() -> Result<(), Error> {
-    let args: Vec<String> = args().collect();
-    if args.len() < 2 {
-        panic!("You must provide a file to the path as first and only argument")
+const USAGE: &str =
+    "Usage: computer_enhance-8086 [disasm [--bytes]] <file> | run <file.com|file.exe> [args...]";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["disasm", path] => disassemble(&read_program(path), false),
+        ["disasm", "--bytes", path] => disassemble(&read_program(path), true),
+        ["run", path, ..] => run(&read_program(path), path, &args[2..]),
+        [path] => emulate(&read_program(path), directory_of(path)),
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    }
+}
+
+fn read_program(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| {
+        eprintln!("error: {}: {}", path, err);
+        exit(1);
+    })
+}
+
+/// The directory the DOS services of a program get to see, the one it is in.
+fn directory_of(path: &str) -> &Path {
+    Path::new(path).parent().unwrap_or(Path::new("."))
+}
+
+/// Prints a listing NASM can assemble back, unless prefixed with offsets and raw bytes.
+fn disassemble(program: &[u8], with_bytes: bool) {
+    let (listing, error) = Dissassembler::new(program).listing(with_bytes);
+    print!("{}", listing);
+    if let Some(err) = error {
+        eprintln!("{}", err);
+        exit(1);
     }
+}
 
-    let mut in_f = File::open(&args[1])?;
-    let mut program = Vec::new();
-    in_f.read_to_end(&mut program)?;
+/// Runs the program until it falls off its end or terminates, DOS services given access to the
+/// files in `directory`.
+fn emulate(program: &[u8], directory: &Path) {
+    let sim = Dissassembler::new(program);
+    let mut emu = Emulator::new();
+    emu.set_interrupt_hook(Dos::new(directory));
+    while (emu.instruction_pointer as usize) < program.len() && emu.termination().is_none() {
+        match sim.get_instruction_at(emu.instruction_pointer as usize) {
+            Ok(inst) => {
+                println!("{}", inst);
+                if let Err(err) = emu.execute_instruction(&inst) {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                exit(1);
+            }
+        }
+    }
+    print!("\nFinal state:\n{}", emu)
+}
 
-    let mut sim = Dissassembler::new(&program);
+/// Runs a .COM or .EXE program until it terminates, only what it prints going to stdout. Exits with the
+/// code the program returned.
+fn run(image: &[u8], path: &str, args: &[String]) {
     let mut emu = Emulator::new();
-    while let Some(inst) = sim.get_instruction_at(emu.instruction_pointer as usize) {
-        emu.execute_instruction(&inst);
-        println!("{:?}", emu)
+    emu.set_interrupt_hook(Dos::new(directory_of(path)));
+    if let Err(err) = load(&mut emu, LOAD_SEGMENT, image, args) {
+        eprintln!("error: {}: {}", path, err);
+        exit(1);
+    }
+    while emu.termination().is_none() {
+        match emu.fetch_instruction() {
+            Ok(inst) => {
+                if let Err(err) = emu.execute_instruction(&inst) {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
+            Err(err) => {
+                let cs = emu.register_value(Register::CS);
+                eprintln!("{:04x}:{:04x}: {}", cs, emu.instruction_pointer, err);
+                exit(1);
+            }
+        }
+    }
+    match emu.termination() {
+        Some(Termination::Exited { code }) => exit(code as i32),
+        _ => exit(0),
     }
-    Ok(())
 }
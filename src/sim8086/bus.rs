@@ -0,0 +1,34 @@
+/// The I/O port space IN and OUT reach, 64 Ki byte ports apart from memory. The host implements
+/// it to attach devices.
+pub trait IoBus {
+    fn read_u8(&mut self, port: u16) -> u8;
+
+    fn write_u8(&mut self, port: u16, value: u8);
+
+    /// A word port is the byte at `port` and the one after it, low byte first.
+    fn read_u16(&mut self, port: u16) -> u16 {
+        u16::from_le_bytes([self.read_u8(port), self.read_u8(port.wrapping_add(1))])
+    }
+
+    fn write_u16(&mut self, port: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_u8(port, low);
+        self.write_u8(port.wrapping_add(1), high);
+    }
+}
+
+/// The bus with no devices on it. Accesses are logged to stderr, and reads see the lines
+/// floating high.
+#[derive(Debug, Default)]
+pub struct UnmappedBus;
+
+impl IoBus for UnmappedBus {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        eprintln!("in from unmapped port {:#06x}", port);
+        0xFF
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        eprintln!("out {:#04x} to unmapped port {:#06x}", value, port);
+    }
+}
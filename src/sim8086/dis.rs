@@ -1,294 +1,502 @@
-use crate::sim8086::opc::{
-    arith::ArithmeticVariant,
-    mov::{Displacement, EffectiveAddress, Register},
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt, iter,
 };
 
 use super::opc::{
-    mov::{get_operand, ImmediateValue, MoveVariant, Operand},
-    parse_opcode, Opcode,
+    control::PrefixVariant,
+    jump::{InterruptVariant, JumpVariant},
+    mov::{displacement_length, get_operand, ImmediateValue, Operand, Register},
+    parse_opcode,
+    string::StringFamily,
+    Opcode,
 };
 
 pub struct Dissassembler<'a> {
     program: &'a [u8],
-    cursor: usize,
 }
 
 impl<'a> Dissassembler<'a> {
     pub fn new(program: &'a [u8]) -> Self {
-        Self { program, cursor: 0 }
+        Self { program }
+    }
+
+    /// Decodes the program front to back, yielding each instruction with its offset. Stops
+    /// after the first error, as there is no telling where the next instruction starts.
+    pub fn instructions(
+        &self,
+    ) -> impl Iterator<Item = Result<(usize, Instruction), DecodeError>> + '_ {
+        let mut position = 0;
+        let mut failed = false;
+        iter::from_fn(move || {
+            if failed || position >= self.program.len() {
+                return None;
+            }
+            let result = self.get_instruction_at(position).map(|instruction| {
+                let at = position;
+                position += instruction.total_bytes as usize;
+                (at, instruction)
+            });
+            failed = result.is_err();
+            Some(result)
+        })
+    }
+
+    /// The listing `disasm` prints, which NASM assembles back to the program: `bits 16`, then
+    /// each instruction on a line, jump targets with a label line of their own. With
+    /// `with_bytes`, lines start with the offset and the bytes of the instruction instead. A
+    /// decode error ends the listing and is returned along with it. ESC is written as the bytes
+    /// it was read from.
+    pub fn listing(&self, with_bytes: bool) -> (String, Option<DecodeError>) {
+        let labels = self.labels();
+        let mut listing = String::from("bits 16\n");
+        for result in self.instructions() {
+            let (offset, instruction) = match result {
+                Ok(decoded) => decoded,
+                Err(err) => return (listing, Some(err)),
+            };
+            if let Some(label) = labels.get(&(offset as u16)) {
+                listing += &format!("{}:\n", label);
+            }
+            let bytes = &self.program[offset..offset + instruction.total_bytes as usize];
+            if with_bytes {
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                listing += &format!("{:04x}: {:<18}", offset, hex.join(" "));
+            }
+            if let Opcode::Escape = instruction.opcode {
+                // NASM only knows the coprocessor instructions by their own names.
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:#04x}", byte)).collect();
+                listing += &format!("db {}\n", hex.join(", "));
+            } else {
+                listing += &format!("{}\n", instruction.display_at(offset, &labels));
+            }
+        }
+        (listing, None)
+    }
+
+    /// Names the targets of relative jumps that land on the start of an instruction, `label_N`
+    /// numbered in address order.
+    pub fn labels(&self) -> BTreeMap<u16, String> {
+        let mut starts = BTreeSet::new();
+        let mut targets = BTreeSet::new();
+        for (offset, instruction) in self.instructions().map_while(Result::ok) {
+            starts.insert(offset as u16);
+            targets.extend(instruction.jump_target(offset));
+        }
+        targets
+            .intersection(&starts)
+            .enumerate()
+            .map(|(n, target)| (*target, format!("label_{}", n)))
+            .collect()
     }
 }
 
 impl<'a> Dissassembler<'a> {
-    pub fn get_instruction_at(&mut self, position: usize) -> Option<Instruction> {
+    pub fn get_instruction_at(&self, position: usize) -> Result<Instruction, DecodeError> {
         if position >= self.program.len() {
-            return None;
+            return Err(DecodeError::Truncated { offset: position });
         };
-        let (first_byte, second_byte, third_byte, forth_byte, fifth_byte, sixth_byte) = (
-            self.program[position],
-            self.program.get(position + 1),
-            self.program.get(position + 2),
-            self.program.get(position + 3),
-            self.program.get(position + 4),
-            self.program.get(position + 5),
-        );
-        let opcode = parse_opcode(first_byte, *second_byte.unwrap());
-        match &opcode {
-            Opcode::Move { variant } => match variant {
-                MoveVariant::RegMemToFromReg => {
-                    let second_byte = *second_byte.unwrap();
-                    let mode = decode_mode(second_byte, 7);
-                    let rm = decode_rm(second_byte, 2);
-                    let w = decode_w(first_byte, 0);
-                    let d = decode_d(first_byte, 1);
-                    let first_operand = Operand::Register(decode_register(
-                        second_byte,
-                        5,
-                        if w == 0b1 {
-                            RegisterSize::SixteenBits
-                        } else {
-                            RegisterSize::EightBits
-                        },
-                    ));
-                    let (second_operand, size) =
-                        get_operand(mode, rm, w, third_byte.copied(), forth_byte.copied());
-                    self.cursor += size as usize;
-                    let mut instruction = Instruction {
-                        opcode,
-                        destination: Some(first_operand),
-                        source: Some(second_operand),
-                        total_bytes: size,
-                    };
-                    if d == 0b0 {
-                        instruction = swap_operands(instruction)
-                    }
-                    Some(instruction)
+        let truncated = DecodeError::Truncated { offset: position };
+        let mut prefixes = Prefixes::default();
+        let mut start = position;
+        let (opcode, fields) = loop {
+            match parse_opcode(self.program, start) {
+                Ok((Opcode::Prefix { variant }, fields)) => {
+                    prefixes.add(variant);
+                    start += fields.length as usize;
                 }
-                MoveVariant::ImmToReg => {
-                    let second_byte = *second_byte.unwrap();
-                    let w = decode_w(first_byte, 3);
-                    let size = if w == 0b1 { 3 } else { 2 };
-                    let first_operand = Operand::Register(decode_register(
-                        first_byte,
-                        2,
-                        if w == 0b1 {
-                            RegisterSize::SixteenBits
-                        } else {
-                            RegisterSize::EightBits
-                        },
-                    ));
-                    let second_operand = Operand::ImmediateValue(if w == 0b1 {
-                        let third_byte = *third_byte.unwrap();
-                        ImmediateValue::SixteenBits(((third_byte as i16) << 8) | second_byte as i16)
-                    } else {
-                        ImmediateValue::EightBits(second_byte as i8)
-                    });
-                    self.cursor += size as usize;
-                    Some(Instruction {
-                        opcode,
-                        destination: Some(first_operand),
-                        source: Some(second_operand),
-                        total_bytes: if w == 0b1 { 3 } else { 2 },
-                    })
-                }
-                MoveVariant::ImmToRegMem => {
-                    let second_byte = *second_byte.unwrap();
-                    let mode = decode_mode(second_byte, 7);
-                    let rm = decode_rm(second_byte, 2);
-                    let w = decode_w(first_byte, 0);
-                    println!("{}, {}, {}", mode, rm, w);
-                    match (mode, rm, w) {
-                        (0b00, 0b110, 0b1) => {
-                            // Direct address, immediate value
-                            let third_byte = *third_byte.unwrap();
-                            let forth_byte = *forth_byte.unwrap();
-                            let fifth_byte = *fifth_byte.unwrap();
-                            let sixth_byte = *sixth_byte.unwrap();
+                Ok(decoded) => break decoded,
+                Err(DecodeError::Truncated { .. }) => return Err(truncated),
+                Err(err) => return Err(err),
+            }
+        };
+        let mut cursor = start + fields.length as usize;
+        let w = fields.w;
+        let register_size = if w == 0b1 {
+            RegisterSize::SixteenBits
+        } else {
+            RegisterSize::EightBits
+        };
 
-                            let addr = ((forth_byte as u16) << 8) | (third_byte as u16);
-                            let value = ((sixth_byte as u16) << 8) | (fifth_byte as u16);
-                            Some(Instruction {
-                                opcode,
-                                destination: Some(Operand::Address(addr as u16)),
-                                source: Some(Operand::ImmediateValue(ImmediateValue::SixteenBits(
-                                    value as i16,
-                                ))),
-                                total_bytes: 6,
-                            })
-                        }
-                        (0b1, 0b111, 0b1) => {
-                            // [BX + <8 bits value>], immediate value
-                            let third_byte = *third_byte.unwrap();
-                            let forth_byte = *forth_byte.unwrap();
-                            let fifth_byte = *fifth_byte.unwrap();
-                            let disp = third_byte;
-                            let value = ((fifth_byte as u16) << 8) | (forth_byte as u16);
-                            println!("value: {}, disp: {}", value, disp);
-                            Some(Instruction {
-                                opcode,
-                                destination: Some(Operand::EffectiveAddress(
-                                    EffectiveAddress::RegisterAndDisplacement(
-                                        Register::BX,
-                                        Displacement::EightBits(disp as i8),
-                                    ),
-                                )),
-                                source: Some(Operand::ImmediateValue(ImmediateValue::SixteenBits(
-                                    value as i16,
-                                ))),
-                                total_bytes: 5,
-                            })
-                        }
-                        _ => todo!(),
-                    }
-                }
-                _ => todo!(),
-            },
-            Opcode::Arithmetic { variant, .. } => match variant {
-                ArithmeticVariant::RegMemAndRegEither => {
-                    let second_byte = *second_byte.unwrap();
-                    let d = decode_d(first_byte, 1);
-                    let w = decode_w(first_byte, 0);
-                    let mode = decode_mode(second_byte, 7);
-                    let rm = decode_rm(second_byte, 2);
+        let reg_operand = match (fields.sr, fields.reg) {
+            (Some(sr), _) => Some(Operand::Register(decode_segment_register(sr))),
+            (None, Some(reg)) => Some(Operand::Register(decode_register(reg, 2, register_size))),
+            (None, None) => None,
+        };
+        let rm_operand = if fields.port_dx {
+            Some(Operand::Register(Register::DX))
+        } else if let Some(mode) = fields.mode {
+            if mode == 0b11 && opcode.requires_memory_operand() {
+                return Err(DecodeError::UnsupportedModRm {
+                    offset: position,
+                    mode,
+                    rm: fields.rm,
+                });
+            }
+            let length = displacement_length(mode, fields.rm);
+            let displacement = self.read(cursor, length).ok_or(truncated)?;
+            cursor += length as usize;
+            Some(get_operand(mode, fields.rm, w, displacement))
+        } else {
+            None
+        };
+        let (mut destination, mut source) = if fields.d == 0b1 {
+            (reg_operand, rm_operand)
+        } else {
+            (rm_operand, reg_operand)
+        };
+        if fields.data {
+            let wide_data = fields.data_if_w && w == 0b1 && fields.s == 0b0;
+            let length = if wide_data { 2 } else { 1 };
+            let value = self.read(cursor, length).ok_or(truncated)?;
+            cursor += length as usize;
+            let immediate = Operand::ImmediateValue(if wide_data {
+                ImmediateValue::SixteenBits(value as i16)
+            } else if fields.s == 0b1 && w == 0b1 {
+                ImmediateValue::SixteenBits(value as i8 as i16)
+            } else {
+                ImmediateValue::EightBits(value as i8)
+            });
+            // Takes the place of the r/m operand, so with d = 0 the port of OUT is the destination.
+            if destination.is_none() {
+                destination = Some(immediate);
+            } else {
+                source = Some(immediate);
+            }
+        }
+        if destination.is_none() {
+            destination = source.take();
+        }
+        if let Some(v) = fields.v {
+            source = Some(if v == 0b1 {
+                Operand::Register(Register::CL)
+            } else {
+                Operand::ImmediateValue(ImmediateValue::EightBits(1))
+            });
+        }
+        if let Some(esc) = fields.esc {
+            source = destination.take();
+            destination = Some(Operand::ImmediateValue(ImmediateValue::EightBits(
+                esc as i8,
+            )));
+        }
+        if fields.relative_disp {
+            let length = if w == 0b1 { 2 } else { 1 };
+            let value = self.read(cursor, length).ok_or(truncated)?;
+            cursor += length as usize;
+            destination = Some(Operand::ImmediateValue(if w == 0b1 {
+                ImmediateValue::SixteenBits(value as i16)
+            } else {
+                ImmediateValue::EightBits(value as i8)
+            }));
+        }
+        if fields.pointer {
+            let offset = self.read(cursor, 2).ok_or(truncated)?;
+            let segment = self.read(cursor + 2, 2).ok_or(truncated)?;
+            cursor += 4;
+            destination = Some(Operand::FarPointer(segment, offset));
+        }
 
-                    let first_operand = Operand::Register(decode_register(
-                        second_byte,
-                        5,
-                        if w == 0b1 {
-                            RegisterSize::SixteenBits
-                        } else {
-                            RegisterSize::EightBits
-                        },
-                    ));
-                    let (second_operand, size) =
-                        get_operand(mode, rm, w, third_byte.copied(), forth_byte.copied());
-                    self.cursor += size as usize;
-                    let mut instruction = Instruction {
-                        opcode,
-                        destination: Some(first_operand),
-                        source: Some(second_operand),
-                        total_bytes: size,
-                    };
-                    if d == 0b0 {
-                        instruction = swap_operands(instruction)
-                    }
-                    Some(instruction)
-                }
-                ArithmeticVariant::ImmAcc => {
-                    let w = decode_w(first_byte, 0);
-                    let second_byte = *second_byte.unwrap();
-                    let first_operand =
-                        Operand::Register(if w == 0b1 { Register::AX } else { Register::AL });
-                    let size: u8;
-                    let second_operand = Operand::ImmediateValue(if w == 0b1 {
-                        let third_byte = *third_byte.unwrap();
-                        size = 3;
-                        ImmediateValue::SixteenBits(
-                            ((third_byte as i16) << 8) | (second_byte as i16),
-                        )
-                    } else {
-                        size = 2;
-                        ImmediateValue::EightBits(second_byte as i8)
-                    });
-                    self.cursor += size as usize;
-                    Some(Instruction {
-                        opcode,
-                        destination: Some(first_operand),
-                        source: Some(second_operand),
-                        total_bytes: size,
-                    })
-                }
-                ArithmeticVariant::ImmRegMem => {
-                    let second_byte = *second_byte.unwrap();
-                    let s = decode_s(first_byte, 1);
-                    let w = decode_w(first_byte, 0);
-                    let mode = decode_mode(second_byte, 7);
-                    let rm = decode_rm(second_byte, 2);
-                    let (first_operand, mut size) =
-                        get_operand(mode, rm, w, third_byte.copied(), forth_byte.copied());
-                    let second_operand = if s == 0b0 && w == 0b1 {
-                        // let fifth_byte = *fifth_byte.unwrap();
-                        // let sixth_byte = *sixth_byte.unwrap();
-                        // size += 2;
-                        // Operand::ImmediateValue(ImmediateValue::SixteenBits(
-                        //     ((sixth_byte as i16) << 8) | fifth_byte as i16,
-                        // ))
-                        let third_byte = *third_byte.unwrap();
-                        let forth_byte = *forth_byte.unwrap();
-                        size += 2;
-                        Operand::ImmediateValue(ImmediateValue::SixteenBits(
-                            ((forth_byte as i16) << 8) | (third_byte as i16),
-                        ))
-                    } else {
-                        let third_byte = *third_byte.unwrap();
-                        size += 1;
-                        Operand::ImmediateValue(ImmediateValue::EightBits(third_byte as i8))
-                    };
-                    let instruction = Instruction {
-                        opcode,
-                        destination: Some(first_operand),
-                        source: Some(second_operand),
-                        total_bytes: size,
-                    };
-                    self.cursor += size as usize;
-                    Some(instruction)
-                }
-            },
-            Opcode::ConditionalJump { .. } => {
-                self.cursor += 2;
-                Some(Instruction {
-                    opcode,
-                    destination: Some(Operand::ImmediateValue(ImmediateValue::EightBits(
-                        *second_byte.unwrap() as i8,
-                    ))),
-                    source: None,
-                    total_bytes: 2,
-                })
+        Ok(Instruction {
+            prefixes,
+            opcode,
+            destination,
+            source,
+            wide: w == 0b1,
+            total_bytes: (cursor - position) as u8,
+        })
+    }
+
+    /// Reads a little-endian value of `length` (0, 1 or 2) bytes, `None` past the end.
+    fn read(&self, at: usize, length: u8) -> Option<u16> {
+        (0..length as usize).try_fold(0, |value, i| {
+            let byte = *self.program.get(at + i)?;
+            Some(value | ((byte as u16) << (8 * i)))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The instruction starting at `offset` runs past the end of the program.
+    Truncated {
+        offset: usize,
+    },
+    UnknownOpcode {
+        offset: usize,
+        byte: u8,
+    },
+    /// A register operand (mod = 11) where the instruction needs a memory one.
+    UnsupportedModRm {
+        offset: usize,
+        mode: u8,
+        rm: u8,
+    },
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match self {
+            DecodeError::Truncated { offset }
+            | DecodeError::UnknownOpcode { offset, .. }
+            | DecodeError::UnsupportedModRm { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated { offset } => {
+                write!(f, "instruction at offset {:#06x} is truncated", offset)
             }
-            _ => todo!(),
+            DecodeError::UnknownOpcode { offset, byte } => {
+                write!(
+                    f,
+                    "unknown opcode byte {:#04x} at offset {:#06x}",
+                    byte, offset
+                )
+            }
+            DecodeError::UnsupportedModRm { offset, mode, rm } => write!(
+                f,
+                "unsupported mod {:02b} with r/m {:03b} at offset {:#06x}",
+                mode, rm, offset
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// The prefixes given before an instruction, which belong to it rather than standing alone.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Prefixes {
+    pub lock: bool,
+    /// `Rep` or `Repne`, the last one given when there are both.
+    pub repeat: Option<PrefixVariant>,
+    /// Segment register replacing the default one of the memory operand.
+    pub segment: Option<Register>,
+}
+
+impl Prefixes {
+    fn add(&mut self, prefix: PrefixVariant) {
+        match prefix {
+            PrefixVariant::Lock => self.lock = true,
+            PrefixVariant::Rep | PrefixVariant::Repne => self.repeat = Some(prefix),
+            PrefixVariant::Segment(register) => self.segment = Some(register),
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Instruction {
+    pub prefixes: Prefixes,
     pub opcode: Opcode,
     pub destination: Option<Operand>,
     pub source: Option<Operand>,
+    pub wide: bool,
     pub total_bytes: u8,
 }
 
-fn swap_operands(instruction: Instruction) -> Instruction {
-    Instruction {
-        opcode: instruction.opcode,
-        destination: instruction.source,
-        source: instruction.destination,
-        total_bytes: instruction.total_bytes,
+impl Instruction {
+    /// Relative jumps keep their displacement as the immediate destination.
+    fn is_relative_jump(&self) -> bool {
+        matches!(
+            self.opcode,
+            Opcode::ConditionalJump { .. }
+                | Opcode::Jump {
+                    variant: JumpVariant::Short | JumpVariant::Near,
+                }
+                | Opcode::Call {
+                    variant: JumpVariant::Near,
+                }
+        )
     }
-}
 
-pub fn decode_w(byte: u8, at: u8) -> u8 {
-    (byte >> at) & 0b1
-}
+    /// Absolute offset a relative jump or call goes to, when the instruction is at `offset`.
+    pub fn jump_target(&self, offset: usize) -> Option<u16> {
+        match &self.destination {
+            Some(Operand::ImmediateValue(value)) if self.is_relative_jump() => Some(
+                (offset as u16)
+                    .wrapping_add(self.total_bytes as u16)
+                    .wrapping_add(value.signed() as u16),
+            ),
+            _ => None,
+        }
+    }
 
-pub fn decode_d(byte: u8, at: u8) -> u8 {
-    (byte >> at) & 0b1
-}
+    /// Displays the instruction as found at `offset`, a relative target as its label when there
+    /// is one and as an absolute offset otherwise.
+    pub fn display_at<'a>(
+        &'a self,
+        offset: usize,
+        labels: &'a BTreeMap<u16, String>,
+    ) -> impl fmt::Display + 'a {
+        InstructionAt {
+            instruction: self,
+            offset,
+            labels,
+        }
+    }
+
+    fn has_unsigned_immediate(&self) -> bool {
+        matches!(
+            self.opcode,
+            Opcode::Input
+                | Opcode::Output
+                | Opcode::Return { .. }
+                | Opcode::Interrupt {
+                    variant: InterruptVariant::Int,
+                }
+        )
+    }
+
+    /// Keyword written before the operands, `byte`/`word` when nothing else gives away the
+    /// size of a memory operand, or the distance of a jump that has to be kept as encoded.
+    fn operand_keyword(&self) -> Option<&'static str> {
+        match self.opcode {
+            Opcode::Jump {
+                variant: JumpVariant::Short,
+            } => return Some("short"),
+            Opcode::Jump {
+                variant: JumpVariant::Near,
+            } => return Some("near"),
+            Opcode::Jump {
+                variant: JumpVariant::FarIndirect,
+            }
+            | Opcode::Call {
+                variant: JumpVariant::FarIndirect,
+            } => return Some("far"),
+            Opcode::Escape => return None,
+            _ => {}
+        }
+        let operands = [&self.destination, &self.source];
+        let has_memory = self.has_memory_operand();
+        // The count of a shift is CL at most, which says nothing about the shifted operand.
+        let sized_by_register = match self.opcode {
+            Opcode::Shift { .. } => matches!(self.destination, Some(Operand::Register(_))),
+            _ => operands
+                .iter()
+                .any(|operand| matches!(operand, Some(Operand::Register(_)))),
+        };
+        match (has_memory && !sized_by_register, self.wide) {
+            (false, _) => None,
+            (true, true) => Some("word"),
+            (true, false) => Some("byte"),
+        }
+    }
+
+    fn has_memory_operand(&self) -> bool {
+        [&self.destination, &self.source]
+            .into_iter()
+            .any(|operand| {
+                matches!(
+                    operand,
+                    Some(Operand::Address(_) | Operand::EffectiveAddress(_))
+                )
+            })
+    }
 
-pub fn decode_s(byte: u8, at: u8) -> u8 {
-    (byte >> at) & 0b1
+    fn fmt_operand(
+        &self,
+        operand: &Operand,
+        at: Option<&InstructionAt>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match (operand, at) {
+            (Operand::ImmediateValue(_), Some(at)) if self.is_relative_jump() => {
+                let target = self.jump_target(at.offset).unwrap();
+                match at.labels.get(&target) {
+                    Some(label) => f.write_str(label),
+                    None => write!(f, "{}", target),
+                }
+            }
+            (Operand::ImmediateValue(value), None) if self.is_relative_jump() => {
+                let offset = value.signed() as i32 + self.total_bytes as i32;
+                if offset < 0 {
+                    write!(f, "$-{}", -offset)
+                } else {
+                    write!(f, "$+{}", offset)
+                }
+            }
+            (Operand::ImmediateValue(value), _) if self.has_unsigned_immediate() => {
+                write!(f, "{}", value.unsigned())
+            }
+            (Operand::Address(_) | Operand::EffectiveAddress(_), _) => {
+                if let Some(segment) = self.prefixes.segment {
+                    write!(f, "{}:", segment)?;
+                }
+                write!(f, "{}", operand)
+            }
+            _ => write!(f, "{}", operand),
+        }
+    }
+
+    fn fmt_with(&self, at: Option<&InstructionAt>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefixes.lock {
+            f.write_str("lock ")?;
+        }
+        match (self.prefixes.repeat, self.opcode) {
+            // F3 repeats while equal for the comparing string instructions.
+            (
+                Some(PrefixVariant::Rep),
+                Opcode::String {
+                    family: StringFamily::Cmps | StringFamily::Scas,
+                },
+            ) => f.write_str("repe ")?,
+            (Some(repeat), _) => write!(f, "{} ", repeat)?,
+            (None, _) => {}
+        }
+        // Without a memory operand to write it on, like the implicit ones of the string
+        // instructions and XLAT, the override stands on its own.
+        let has_memory = self.has_memory_operand();
+        if let (Some(segment), false) = (self.prefixes.segment, has_memory) {
+            write!(f, "{} ", segment)?;
+        }
+        write!(f, "{}", self.opcode)?;
+        if let Opcode::String { .. } = self.opcode {
+            f.write_str(if self.wide { "w" } else { "b" })?;
+        }
+        let mut keyword = self.operand_keyword();
+        let mut separator = " ";
+        for operand in [&self.destination, &self.source].into_iter().flatten() {
+            f.write_str(separator)?;
+            separator = ", ";
+            let is_keyword_target = match operand {
+                Operand::Address(_) | Operand::EffectiveAddress(_) => true,
+                Operand::ImmediateValue(_) => self.is_relative_jump(),
+                _ => false,
+            };
+            if is_keyword_target {
+                if let Some(keyword) = keyword.take() {
+                    write!(f, "{} ", keyword)?;
+                }
+            }
+            self.fmt_operand(operand, at, f)?;
+        }
+        Ok(())
+    }
 }
 
-pub fn decode_reg(byte: u8, from: u8) -> u8 {
-    (byte >> (from - 2)) & 0b111
+/// NASM syntax, e.g. `mov word [bx + di - 37], 4096` or `rep movsb`. Relative targets are
+/// written as `$+n`, as nothing tells where the instruction is.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(None, f)
+    }
 }
 
-pub fn decode_rm(byte: u8, from: u8) -> u8 {
-    (byte >> (from - 2)) & 0b111
+struct InstructionAt<'a> {
+    instruction: &'a Instruction,
+    offset: usize,
+    labels: &'a BTreeMap<u16, String>,
 }
 
-pub fn decode_mode(byte: u8, from: u8) -> u8 {
-    (byte >> (from - 1)) & 0b11
+impl fmt::Display for InstructionAt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.instruction.fmt_with(Some(self), f)
+    }
 }
 
 pub enum RegisterSize {
@@ -322,3 +530,13 @@ pub fn decode_register(byte: u8, from: u8, size: RegisterSize) -> Register {
         },
     }
 }
+
+pub fn decode_segment_register(value: u8) -> Register {
+    match value & 0b11 {
+        0b00 => Register::ES,
+        0b01 => Register::CS,
+        0b10 => Register::SS,
+        0b11 => Register::DS,
+        _ => panic!(),
+    }
+}
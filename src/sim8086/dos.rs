@@ -0,0 +1,270 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use super::{
+    emu::{Emulator, InterruptHook, Termination},
+    opc::mov::Register,
+};
+
+/// DOS error codes, returned in AX with CF set.
+const INVALID_FUNCTION: u16 = 0x01;
+const FILE_NOT_FOUND: u16 = 0x02;
+const PATH_NOT_FOUND: u16 = 0x03;
+const TOO_MANY_OPEN_FILES: u16 = 0x04;
+const ACCESS_DENIED: u16 = 0x05;
+const INVALID_HANDLE: u16 = 0x06;
+
+/// Handles 0 to 4 are the standard devices, files get the ones after.
+const FIRST_FILE_HANDLE: u16 = 5;
+
+/// What DOS reads as the end of the input.
+const END_OF_FILE: u8 = 0x1A;
+
+/// The DOS services, INT 21h and INT 20h, run by the host. Console I/O goes to the given input
+/// and output, and writes to handle 2 to the error output. Files are looked up in the `root`
+/// directory and can't be outside of it.
+pub struct Dos {
+    root: PathBuf,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    error: Box<dyn Write>,
+    files: BTreeMap<u16, File>,
+}
+
+impl Dos {
+    /// Uses the standard input, output and error of the process for the console.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self::with_io(
+            root,
+            BufReader::new(io::stdin()),
+            io::stdout(),
+            io::stderr(),
+        )
+    }
+
+    pub fn with_io(
+        root: impl Into<PathBuf>,
+        input: impl BufRead + 'static,
+        output: impl Write + 'static,
+        error: impl Write + 'static,
+    ) -> Self {
+        Dos {
+            root: root.into(),
+            input: Box::new(input),
+            output: Box::new(output),
+            error: Box::new(error),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Runs function AH of INT 21h.
+    fn call(&mut self, emulator: &mut Emulator) {
+        let ah = emulator.register_value(Register::AH);
+        match ah {
+            0x00 => emulator.terminate(Termination::Exited { code: 0 }),
+            0x01 | 0x08 => {
+                let byte = self.read_byte();
+                if ah == 0x01 {
+                    self.write_output(&[byte]);
+                }
+                emulator.set_register_value(Register::AL, byte as u16);
+            }
+            0x02 => {
+                let byte = emulator.register_value(Register::DL) as u8;
+                self.write_output(&[byte]);
+                emulator.set_register_value(Register::AL, byte as u16);
+            }
+            0x09 => {
+                let (ds, dx) = data_pointer(emulator);
+                let string: Vec<u8> = (0..=0xFFFF)
+                    .map(|i| emulator.memory().read_u8(ds, dx.wrapping_add(i)))
+                    .take_while(|byte| *byte != b'$')
+                    .collect();
+                self.write_output(&string);
+                emulator.set_register_value(Register::AL, b'$' as u16);
+            }
+            0x0A => self.read_line(emulator),
+            0x3D..=0x42 => {
+                let result = self.file_call(emulator, ah as u8);
+                emulator.flags_mut().set_carry(result.is_err());
+                let ax = result.unwrap_or_else(|code| code);
+                emulator.set_register_value(Register::AX, ax);
+            }
+            0x4C => {
+                let code = emulator.register_value(Register::AL) as u8;
+                emulator.terminate(Termination::Exited { code });
+            }
+            _ => {
+                emulator.flags_mut().set_carry(true);
+                emulator.set_register_value(Register::AX, INVALID_FUNCTION);
+            }
+        }
+    }
+
+    /// Function 0Ah, a line into the buffer at DS:DX: its first byte is the room for the line,
+    /// carriage return included, the second one gets the length without it.
+    fn read_line(&mut self, emulator: &mut Emulator) {
+        let (ds, dx) = data_pointer(emulator);
+        let room = emulator.memory().read_u8(ds, dx);
+        if room == 0 {
+            return;
+        }
+        let mut line = Vec::new();
+        // An input that fails or ends reads as an empty line.
+        let _ = self.input.read_until(b'\n', &mut line);
+        while let Some(b'\n' | b'\r') = line.last() {
+            line.pop();
+        }
+        line.truncate(room as usize - 1);
+        let memory = emulator.memory_mut();
+        memory.write_u8(ds, dx.wrapping_add(1), line.len() as u8);
+        for (i, byte) in line.iter().chain([&b'\r']).enumerate() {
+            memory.write_u8(ds, dx.wrapping_add(2 + i as u16), *byte);
+        }
+    }
+
+    /// Functions 3Dh to 42h, returning AX or the error code.
+    fn file_call(&mut self, emulator: &mut Emulator, function: u8) -> Result<u16, u16> {
+        let (ds, dx) = data_pointer(emulator);
+        let bx = emulator.register_value(Register::BX);
+        let cx = emulator.register_value(Register::CX);
+        let al = emulator.register_value(Register::AL);
+        match function {
+            0x3D => {
+                let path = self.resolve(&read_asciiz(emulator, ds, dx))?;
+                let mut options = OpenOptions::new();
+                match al & 0b11 {
+                    0 => options.read(true),
+                    1 => options.write(true),
+                    2 => options.read(true).write(true),
+                    _ => return Err(ACCESS_DENIED),
+                };
+                let file = options.open(path).map_err(error_code)?;
+                let handle = (FIRST_FILE_HANDLE..=u16::MAX)
+                    .find(|handle| !self.files.contains_key(handle))
+                    .ok_or(TOO_MANY_OPEN_FILES)?;
+                self.files.insert(handle, file);
+                Ok(handle)
+            }
+            0x3E => self.files.remove(&bx).map(|_| 0).ok_or(INVALID_HANDLE),
+            0x3F => {
+                let mut buffer = vec![0; cx as usize];
+                let count = match bx {
+                    0 => self.input.read(&mut buffer),
+                    _ => self.file(bx)?.read(&mut buffer),
+                }
+                .map_err(error_code)?;
+                for (i, byte) in buffer[..count].iter().enumerate() {
+                    emulator
+                        .memory_mut()
+                        .write_u8(ds, dx.wrapping_add(i as u16), *byte);
+                }
+                Ok(count as u16)
+            }
+            0x40 => {
+                let buffer: Vec<u8> = (0..cx)
+                    .map(|i| emulator.memory().read_u8(ds, dx.wrapping_add(i)))
+                    .collect();
+                match bx {
+                    1 => self
+                        .output
+                        .write_all(&buffer)
+                        .and_then(|_| self.output.flush()),
+                    2 => self
+                        .error
+                        .write_all(&buffer)
+                        .and_then(|_| self.error.flush()),
+                    _ => self.file(bx)?.write_all(&buffer),
+                }
+                .map_err(error_code)?;
+                Ok(cx)
+            }
+            0x41 => {
+                let path = self.resolve(&read_asciiz(emulator, ds, dx))?;
+                fs::remove_file(path).map(|_| 0).map_err(error_code)
+            }
+            0x42 => {
+                let offset = (((cx as u32) << 16) | dx as u32) as i32;
+                let position = match al {
+                    0 => SeekFrom::Start(offset as u32 as u64),
+                    1 => SeekFrom::Current(offset as i64),
+                    2 => SeekFrom::End(offset as i64),
+                    _ => return Err(INVALID_FUNCTION),
+                };
+                let position = self.file(bx)?.seek(position).map_err(error_code)?;
+                emulator.set_register_value(Register::DX, (position >> 16) as u16);
+                Ok(position as u16)
+            }
+            _ => Err(INVALID_FUNCTION),
+        }
+    }
+
+    fn file(&mut self, handle: u16) -> Result<&mut File, u16> {
+        self.files.get_mut(&handle).ok_or(INVALID_HANDLE)
+    }
+
+    /// Host path of a DOS file name, which has to stay inside the root directory.
+    fn resolve(&self, name: &str) -> Result<PathBuf, u16> {
+        let name = name.replace('\\', "/");
+        let mut path = self.root.clone();
+        for component in Path::new(&name).components() {
+            match component {
+                Component::Normal(part) if !part.to_string_lossy().contains(':') => path.push(part),
+                Component::RootDir | Component::CurDir => {}
+                _ => return Err(PATH_NOT_FOUND),
+            }
+        }
+        Ok(path)
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [END_OF_FILE];
+        match self.input.read(&mut byte) {
+            Ok(1) => byte[0],
+            _ => END_OF_FILE,
+        }
+    }
+
+    fn write_output(&mut self, bytes: &[u8]) {
+        // Like the console, there is nowhere to report a failed write to.
+        let _ = self.output.write_all(bytes);
+        let _ = self.output.flush();
+    }
+}
+
+impl InterruptHook for Dos {
+    fn interrupt(&mut self, emulator: &mut Emulator, vector: u8) -> bool {
+        match vector {
+            0x20 => emulator.terminate(Termination::Exited { code: 0 }),
+            0x21 => self.call(emulator),
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn data_pointer(emulator: &Emulator) -> (u16, u16) {
+    (
+        emulator.register_value(Register::DS),
+        emulator.register_value(Register::DX),
+    )
+}
+
+fn read_asciiz(emulator: &Emulator, segment: u16, offset: u16) -> String {
+    let bytes: Vec<u8> = (0..=0xFFFF)
+        .map(|i| emulator.memory().read_u8(segment, offset.wrapping_add(i)))
+        .take_while(|byte| *byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn error_code(err: io::Error) -> u16 {
+    match err.kind() {
+        io::ErrorKind::NotFound => FILE_NOT_FOUND,
+        _ => ACCESS_DENIED,
+    }
+}
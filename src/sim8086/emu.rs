@@ -1,21 +1,50 @@
 use core::fmt;
+use std::error::Error;
 
 use crate::sim8086::opc::cond_jump::ConditionalJumpVariant;
 
 use super::{
-    dis::Instruction,
+    bus::{IoBus, UnmappedBus},
+    dis::{DecodeError, Dissassembler, Instruction},
+    mem::Memory,
     opc::{
-        arith::ArithmeticFamily,
-        mov::{ImmediateValue, MoveVariant, Operand, Register},
+        arith::{AdjustFamily, ArithmeticFamily, UnaryFamily},
+        control::{PrefixVariant, ProcessorFamily},
+        jump::{InterruptVariant, JumpVariant, ReturnVariant},
+        logic::ShiftFamily,
+        mov::{Displacement, EffectiveAddress, ImmediateValue, Operand, Register},
+        string::StringFamily,
+        transfer::TransferFamily,
         Opcode,
     },
 };
 
+/// Lets the host run interrupts itself, in place of the handlers in the vector table.
+pub trait InterruptHook {
+    /// Runs interrupt `vector` with the instruction pointer already past the instruction that
+    /// raised it. Returns false to leave it to the handler in the vector table.
+    fn interrupt(&mut self, emulator: &mut Emulator, vector: u8) -> bool;
+}
+
+/// Why the emulator stopped running instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// A HLT, with nothing to wake the processor up.
+    Halted,
+    /// The program asked the host to end it.
+    Exited { code: u8 },
+}
+
 pub struct Emulator {
     registers: EmulatorRegisters,
-    flags: EmulatorFlags,
+    flags: FlagsRegister,
     pub instruction_pointer: u16,
-    memory: [u8; 65536],
+    memory: Memory,
+    /// Segment override prefix of the instruction being executed.
+    segment_override: Option<Register>,
+    interrupt_hook: Option<Box<dyn InterruptHook>>,
+    io_bus: Box<dyn IoBus>,
+    termination: Option<Termination>,
 }
 
 impl fmt::Debug for Emulator {
@@ -24,33 +53,178 @@ impl fmt::Debug for Emulator {
             .field("registers", &self.registers)
             .field("flags", &self.flags)
             .field("ip", &self.instruction_pointer)
-            .field("memory", &self.memory.iter().enumerate().filter(|(_, b)| **b != 0).collect::<Vec<(usize, &u8)>>())
+            .field("memory", &self.memory)
             .finish()
     }
 }
 
+/// Bytes read to decode one instruction: the longest one takes 6, leaving room for prefixes.
+const MAX_FETCH: u16 = 16;
+
+/// The state as the `test/*.state` files expect it, zero registers and memory are left out.
+impl fmt::Display for Emulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for register in [
+            Register::AX,
+            Register::BX,
+            Register::CX,
+            Register::DX,
+            Register::SP,
+            Register::BP,
+            Register::SI,
+            Register::DI,
+            Register::ES,
+            Register::CS,
+            Register::SS,
+            Register::DS,
+        ] {
+            let value = self.get_register(register) as u16;
+            if value != 0 {
+                writeln!(f, "{}: {:#06x} ({})", register, value, value)?;
+            }
+        }
+        let ip = self.instruction_pointer;
+        writeln!(f, "ip: {:#06x} ({})", ip, ip)?;
+        match self.flags.to_string().as_str() {
+            "" => writeln!(f, "flags:")?,
+            flags => writeln!(f, "flags: {}", flags)?,
+        }
+        for (address, byte) in self.memory.non_zero() {
+            writeln!(f, "[{:#06x}]: {:#04x} ({})", address, byte, byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Emulator {
     pub fn new() -> Self {
         Emulator {
-            flags: EmulatorFlags::new(),
+            flags: FlagsRegister::new(),
             registers: EmulatorRegisters::new(),
             instruction_pointer: 0,
-            memory: [0; 65536],
+            memory: Memory::new(),
+            segment_override: None,
+            interrupt_hook: None,
+            io_bus: Box::new(UnmappedBus),
+            termination: None,
+        }
+    }
+
+    /// Stops the emulator, for the host to see once the instruction is over.
+    pub fn terminate(&mut self, termination: Termination) {
+        self.termination = Some(termination);
+    }
+
+    pub fn termination(&self) -> Option<Termination> {
+        self.termination
+    }
+
+    pub fn set_interrupt_hook(&mut self, hook: impl InterruptHook + 'static) {
+        self.interrupt_hook = Some(Box::new(hook));
+    }
+
+    /// Attaches the devices IN and OUT talk to, in place of the `UnmappedBus`.
+    pub fn set_io_bus(&mut self, bus: impl IoBus + 'static) {
+        self.io_bus = Box::new(bus);
+    }
+
+    /// Decodes the instruction at CS:IP from memory, for programs loaded there rather than run
+    /// from a separate buffer. Offsets in errors are relative to IP.
+    pub fn fetch_instruction(&self) -> Result<Instruction, DecodeError> {
+        let cs = self.get_register(Register::CS) as u16;
+        let bytes: Vec<u8> = (0..MAX_FETCH)
+            .map(|i| {
+                self.memory
+                    .read_u8(cs, self.instruction_pointer.wrapping_add(i))
+            })
+            .collect();
+        Dissassembler::new(&bytes).get_instruction_at(0)
+    }
+
+    /// Value of any register, 8-bit ones zero extended.
+    pub fn register_value(&self, register: Register) -> u16 {
+        let value = self.get_operand_value(&Operand::Register(register), true) as u16;
+        match register {
+            Register::AL
+            | Register::BL
+            | Register::CL
+            | Register::DL
+            | Register::AH
+            | Register::BH
+            | Register::CH
+            | Register::DH => value & 0xFF,
+            _ => value,
         }
     }
 
-    fn load_from_memory(&self, from: usize) -> u16 {
-        let first_byte = self.memory[from];
-        let second_byte = self.memory[from + 1];
-        ((second_byte as u16) << 8) | (first_byte as u16)
+    /// Sets any register, 8-bit ones from the low byte of `value`.
+    pub fn set_register_value(&mut self, register: Register, value: u16) {
+        self.set_operand_value(&Operand::Register(register), value as i16, true)
+    }
+
+    pub fn flags(&self) -> &FlagsRegister {
+        &self.flags
+    }
+
+    pub fn flags_mut(&mut self) -> &mut FlagsRegister {
+        &mut self.flags
     }
 
-    fn store_into_memory(&mut self, to: usize, value: u16) {
-        self.memory[to] = ((value & 0xFF00) >> 8) as u8;
-        self.memory[to + 1] = (value & 0x00FF) as u8;
+    pub fn memory(&self) -> &Memory {
+        &self.memory
     }
 
-    fn get_operand_value(&self, operand: &Operand) -> i16 {
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Segment and offset a memory operand points to. The offset is base + index + displacement
+    /// wrapping at 16 bits, the segment the one of an override prefix if any, else SS when BP is
+    /// the base and DS otherwise.
+    fn memory_address(&self, operand: &Operand) -> Option<(u16, u16)> {
+        let displacement = |disp: &Displacement| match disp {
+            Displacement::EightBits(value) => *value as u16,
+            Displacement::SixteenBits(value) => *value as u16,
+        };
+        let register = |register: &Register| self.get_register(*register) as u16;
+        let offset = match operand {
+            Operand::Address(address) => *address,
+            Operand::EffectiveAddress(address) => match address {
+                EffectiveAddress::JustRegister(base) => register(base),
+                EffectiveAddress::RegisterAndOffset(base, offset) => {
+                    register(base).wrapping_add(register(offset))
+                }
+                EffectiveAddress::RegisterAndDisplacement(base, disp) => {
+                    register(base).wrapping_add(displacement(disp))
+                }
+                EffectiveAddress::RegisterOffsetAndDisplacement(base, offset, disp) => {
+                    register(base)
+                        .wrapping_add(register(offset))
+                        .wrapping_add(displacement(disp))
+                }
+            },
+            _ => return None,
+        };
+        let segment = self.segment_override.unwrap_or(match operand {
+            Operand::EffectiveAddress(
+                EffectiveAddress::JustRegister(Register::BP)
+                | EffectiveAddress::RegisterAndOffset(Register::BP, _)
+                | EffectiveAddress::RegisterAndDisplacement(Register::BP, _)
+                | EffectiveAddress::RegisterOffsetAndDisplacement(Register::BP, _, _),
+            ) => Register::SS,
+            _ => Register::DS,
+        });
+        Some((self.get_register(segment) as u16, offset))
+    }
+
+    /// Value of an operand, memory ones read as a byte or a word depending on `wide`.
+    fn get_operand_value(&self, operand: &Operand, wide: bool) -> i16 {
         match operand {
             Operand::ImmediateValue(imm_val) => match imm_val {
                 ImmediateValue::SixteenBits(val) => *val,
@@ -62,40 +236,25 @@ impl Emulator {
                 Register::CL => self.registers.reg_c.get_low() as i16,
                 Register::DL => self.registers.reg_d.get_low() as i16,
                 Register::AH => self.registers.reg_a.get_high() as i16,
-                Register::BH => self.registers.reg_b.get_low() as i16,
+                Register::BH => self.registers.reg_b.get_high() as i16,
                 Register::CH => self.registers.reg_c.get_high() as i16,
                 Register::DH => self.registers.reg_d.get_high() as i16,
-                Register::AX => self.registers.reg_a.get(),
-                Register::BX => self.registers.reg_b.get(),
-                Register::CX => self.registers.reg_c.get(),
-                Register::DX => self.registers.reg_d.get(),
-                Register::SP => self.registers.reg_sp.get(),
-                Register::BP => self.registers.reg_bp.get(),
-                Register::SI => self.registers.reg_si.get(),
-                Register::DI => self.registers.reg_di.get(),
+                _ => self.get_register(*reg),
             },
-            _ => todo!(),
+            Operand::Address(_) | Operand::EffectiveAddress(_) => {
+                let (segment, offset) = self.memory_address(operand).unwrap();
+                if wide {
+                    self.memory.read_u16(segment, offset) as i16
+                } else {
+                    self.memory.read_u8(segment, offset) as i8 as i16
+                }
+            }
+            Operand::FarPointer(..) => {
+                unreachable!("far pointers have no value of their own, see far_pointer")
+            }
         }
     }
 
-    fn get_register_low(&self, register: Register) -> i8 {
-        match register {
-            Register::AL => self.registers.reg_a.get_low(),
-            Register::BL => self.registers.reg_b.get_low(),
-            Register::CL => self.registers.reg_c.get_low(),
-            Register::DL => self.registers.reg_d.get_low(),
-            _ => panic!("Can't get low part of {:?}", register),
-        }
-    }
-    fn get_register_high(&self, register: Register) -> i8 {
-        match register {
-            Register::AH => self.registers.reg_a.get_high(),
-            Register::BH => self.registers.reg_b.get_high(),
-            Register::CH => self.registers.reg_c.get_high(),
-            Register::DH => self.registers.reg_d.get_high(),
-            _ => panic!("Can't get high part of {:?}", register),
-        }
-    }
     fn get_register(&self, register: Register) -> i16 {
         match register {
             Register::AX => self.registers.reg_a.get(),
@@ -106,6 +265,10 @@ impl Emulator {
             Register::BP => self.registers.reg_bp.get(),
             Register::DI => self.registers.reg_di.get(),
             Register::SI => self.registers.reg_si.get(),
+            Register::ES => self.registers.reg_es.get(),
+            Register::CS => self.registers.reg_cs.get(),
+            Register::SS => self.registers.reg_ss.get(),
+            Register::DS => self.registers.reg_ds.get(),
             _ => panic!("Can't get whole register value for {:?}", register),
         }
     }
@@ -139,135 +302,746 @@ impl Emulator {
             Register::BP => self.registers.reg_bp.set(value),
             Register::DI => self.registers.reg_di.set(value),
             Register::SI => self.registers.reg_si.set(value),
+            Register::ES => self.registers.reg_es.set(value),
+            Register::CS => self.registers.reg_cs.set(value),
+            Register::SS => self.registers.reg_ss.set(value),
+            Register::DS => self.registers.reg_ds.set(value),
             _ => panic!("Whole register value for {:?} can't be set", register),
         }
     }
 
-    pub fn execute_instruction(&mut self, instruction: &Instruction) {
-        self.instruction_pointer += instruction.total_bytes as u16;
+    fn set_operand_value(&mut self, operand: &Operand, value: i16, wide: bool) {
+        match operand {
+            Operand::Register(reg) => match reg {
+                Register::AL | Register::BL | Register::CL | Register::DL => {
+                    self.set_register_low(*reg, value as i8)
+                }
+                Register::AH | Register::BH | Register::CH | Register::DH => {
+                    self.set_register_high(*reg, value as i8)
+                }
+                _ => self.set_register(*reg, value),
+            },
+            Operand::Address(_) | Operand::EffectiveAddress(_) => {
+                let (segment, offset) = self.memory_address(operand).unwrap();
+                if wide {
+                    self.memory.write_u16(segment, offset, value as u16)
+                } else {
+                    self.memory.write_u8(segment, offset, value as u8)
+                }
+            }
+            _ => panic!("Can't store a value into {:?}", operand),
+        }
+    }
+
+    /// Computes `dest <family> source` over 8 or 16 bits, updating the flags, and returns the
+    /// result. CMP and TEST return it too, it is up to the caller not to store it.
+    fn compute_arithmetic(
+        &mut self,
+        family: ArithmeticFamily,
+        dest: u16,
+        source: u16,
+        wide: bool,
+    ) -> u16 {
+        let (mask, sign_bit) = if wide { (0xFFFF, 0x8000) } else { (0xFF, 0x80) };
+        let dest = dest as u32 & mask;
+        let source = source as u32 & mask;
+        let carry = self.flags.carry() as u32;
+        let result = match family {
+            ArithmeticFamily::Add => dest + source,
+            ArithmeticFamily::Adc => dest + source + carry,
+            ArithmeticFamily::Sub | ArithmeticFamily::Cmp => dest.wrapping_sub(source),
+            ArithmeticFamily::Sbb => dest.wrapping_sub(source).wrapping_sub(carry),
+            ArithmeticFamily::And | ArithmeticFamily::Test => dest & source,
+            ArithmeticFamily::Or => dest | source,
+            ArithmeticFamily::Xor => dest ^ source,
+        };
+        // Logical operations always clear the carry, for the others anything above the mask
+        // is either a carry out of, or a borrow into, the most significant bit.
+        self.flags.set_carry(result > mask);
+        self.flags.set_overflow(match family {
+            // Both operands have the same sign and the result has the other one.
+            ArithmeticFamily::Add | ArithmeticFamily::Adc => {
+                (dest ^ result) & (source ^ result) & sign_bit != 0
+            }
+            // The operands have different signs and the result does not keep the destination's.
+            ArithmeticFamily::Sub | ArithmeticFamily::Sbb | ArithmeticFamily::Cmp => {
+                (dest ^ source) & (dest ^ result) & sign_bit != 0
+            }
+            _ => false,
+        });
+        self.flags.set_auxiliary_carry(match family {
+            ArithmeticFamily::And
+            | ArithmeticFamily::Test
+            | ArithmeticFamily::Or
+            | ArithmeticFamily::Xor => false,
+            // Carry out of, or borrow into, bit 3.
+            _ => (dest ^ source ^ result) & 0x10 != 0,
+        });
+        self.set_result_flags((result & mask) as u16, wide);
+        (result & mask) as u16
+    }
+
+    /// Sets SF, ZF and PF from a result of 8 or 16 bits.
+    fn set_result_flags(&mut self, result: u16, wide: bool) {
+        let sign_bit = if wide { 0x8000 } else { 0x80 };
+        self.flags.set_sign(result & sign_bit != 0);
+        self.flags.set_zero(result == 0);
+        // Parity only ever looks at the low byte.
+        self.flags
+            .set_parity((result & 0xFF).count_ones().is_multiple_of(2));
+    }
+
+    /// Adjusts AL, and AH for the ASCII forms, after an operation on packed (DAA, DAS) or
+    /// unpacked (AAA, AAS, AAM, AAD) BCD digits.
+    fn adjust(&mut self, family: AdjustFamily) {
+        let [al, ah] = (self.get_register(Register::AX) as u16).to_le_bytes();
+        let low_digit_overflowed = al & 0x0F > 9 || self.flags.auxiliary_carry();
+        let (al, ah) = match family {
+            AdjustFamily::Daa | AdjustFamily::Das => {
+                let carry = self.flags.carry();
+                let mut result = al;
+                self.flags.set_carry(false);
+                self.flags.set_auxiliary_carry(low_digit_overflowed);
+                if low_digit_overflowed {
+                    let (adjusted, out) = if family == AdjustFamily::Daa {
+                        result.overflowing_add(0x06)
+                    } else {
+                        result.overflowing_sub(0x06)
+                    };
+                    result = adjusted;
+                    self.flags.set_carry(carry || out);
+                }
+                if al > 0x99 || carry {
+                    result = if family == AdjustFamily::Daa {
+                        result.wrapping_add(0x60)
+                    } else {
+                        result.wrapping_sub(0x60)
+                    };
+                    self.flags.set_carry(true);
+                }
+                self.set_result_flags(result as u16, false);
+                (result, ah)
+            }
+            AdjustFamily::Aaa | AdjustFamily::Aas => {
+                self.flags.set_auxiliary_carry(low_digit_overflowed);
+                self.flags.set_carry(low_digit_overflowed);
+                // The 8086 adjusts AL alone, later processors carry into AH.
+                let (al, ah) = match (low_digit_overflowed, family) {
+                    (false, _) => (al, ah),
+                    (true, AdjustFamily::Aaa) => (al.wrapping_add(6), ah.wrapping_add(1)),
+                    (true, _) => (al.wrapping_sub(6), ah.wrapping_sub(1)),
+                };
+                (al & 0x0F, ah)
+            }
+            AdjustFamily::Aam => {
+                self.set_result_flags((al % 10) as u16, false);
+                (al % 10, al / 10)
+            }
+            AdjustFamily::Aad => {
+                let result = al.wrapping_add(ah.wrapping_mul(10));
+                self.set_result_flags(result as u16, false);
+                (result, 0)
+            }
+        };
+        self.set_register(Register::AX, u16::from_le_bytes([al, ah]) as i16);
+    }
+
+    /// Shifts or rotates `value` `count` times a bit. A zero count changes nothing, rotates only
+    /// touch CF and OF, and OF is computed as for a single bit, as the 8086 does.
+    fn compute_shift(&mut self, family: ShiftFamily, value: u16, count: u8, wide: bool) -> u16 {
+        if count == 0 {
+            return value;
+        }
+        let (mask, sign_bit) = if wide { (0xFFFF, 0x8000) } else { (0xFF, 0x80) };
+        let mut result = value & mask;
+        let mut previous = result;
+        let mut carry = self.flags.carry();
+        for _ in 0..count {
+            previous = result;
+            let (msb, lsb) = (result & sign_bit != 0, result & 1 != 0);
+            result = match family {
+                ShiftFamily::Rol => (result << 1) | msb as u16,
+                ShiftFamily::Ror => (result >> 1) | if lsb { sign_bit } else { 0 },
+                ShiftFamily::Rcl => (result << 1) | carry as u16,
+                ShiftFamily::Rcr => (result >> 1) | if carry { sign_bit } else { 0 },
+                ShiftFamily::ShlSal => result << 1,
+                ShiftFamily::Shr => result >> 1,
+                ShiftFamily::Sar => (result >> 1) | (result & sign_bit),
+            } & mask;
+            carry = match family {
+                ShiftFamily::Rol | ShiftFamily::Rcl | ShiftFamily::ShlSal => msb,
+                _ => lsb,
+            };
+        }
+        let msb = result & sign_bit != 0;
+        self.flags.set_carry(carry);
+        self.flags.set_overflow(match family {
+            // The sign changed with the bit that went out.
+            ShiftFamily::Rol | ShiftFamily::Rcl | ShiftFamily::ShlSal => msb != carry,
+            // The two top bits differ, the sign changed with the bit that came in.
+            ShiftFamily::Ror | ShiftFamily::Rcr => msb != (result & (sign_bit >> 1) != 0),
+            // The sign bit the last step shifted out of place.
+            ShiftFamily::Shr => previous & sign_bit != 0,
+            ShiftFamily::Sar => false,
+        });
+        if let ShiftFamily::ShlSal | ShiftFamily::Shr | ShiftFamily::Sar = family {
+            self.set_result_flags(result, wide);
+        }
+        result
+    }
+
+    fn read_memory(&self, segment: u16, offset: u16, wide: bool) -> u16 {
+        if wide {
+            self.memory.read_u16(segment, offset)
+        } else {
+            self.memory.read_u8(segment, offset) as u16
+        }
+    }
+
+    fn write_memory(&mut self, segment: u16, offset: u16, value: u16, wide: bool) {
+        if wide {
+            self.memory.write_u16(segment, offset, value)
+        } else {
+            self.memory.write_u8(segment, offset, value as u8)
+        }
+    }
+
+    /// Pushes a word onto the stack at SS:SP, SP going down first.
+    fn push(&mut self, value: u16) {
+        let sp = (self.get_register(Register::SP) as u16).wrapping_sub(2);
+        self.set_register(Register::SP, sp as i16);
+        let ss = self.get_register(Register::SS) as u16;
+        self.memory.write_u16(ss, sp, value);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let sp = self.get_register(Register::SP) as u16;
+        let ss = self.get_register(Register::SS) as u16;
+        let value = self.memory.read_u16(ss, sp);
+        self.set_register(Register::SP, sp.wrapping_add(2) as i16);
+        value
+    }
+
+    /// Segment and offset of a far pointer, either given directly or stored as offset then
+    /// segment at a memory operand.
+    fn far_pointer(&self, operand: &Operand) -> (u16, u16) {
+        match operand {
+            Operand::FarPointer(segment, offset) => (*segment, *offset),
+            _ => {
+                let (segment, offset) = self.memory_address(operand).unwrap();
+                (
+                    self.memory.read_u16(segment, offset.wrapping_add(2)),
+                    self.memory.read_u16(segment, offset),
+                )
+            }
+        }
+    }
+
+    /// Runs a string instruction once, or with a repeat prefix as long as CX isn't zero, CMPS
+    /// and SCAS stopping early once ZF disagrees with the prefix.
+    fn execute_string(&mut self, family: StringFamily, wide: bool, repeat: Option<PrefixVariant>) {
+        let Some(repeat) = repeat else {
+            return self.execute_string_step(family, wide);
+        };
+        while self.get_register(Register::CX) != 0 {
+            self.execute_string_step(family, wide);
+            let count = self.get_register(Register::CX).wrapping_sub(1);
+            self.set_register(Register::CX, count);
+            if let StringFamily::Cmps | StringFamily::Scas = family {
+                if self.flags.zero() != (repeat == PrefixVariant::Rep) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// One element of a string instruction: the source at DS:SI, or the segment of an override,
+    /// the destination at ES:DI, both indexes moving by the element size up or down as DF says.
+    fn execute_string_step(&mut self, family: StringFamily, wide: bool) {
+        let size: u16 = if wide { 2 } else { 1 };
+        let step = if self.flags.direction() {
+            size.wrapping_neg()
+        } else {
+            size
+        };
+        let accumulator = if wide { Register::AX } else { Register::AL };
+        let source_segment =
+            self.get_register(self.segment_override.unwrap_or(Register::DS)) as u16;
+        let destination_segment = self.get_register(Register::ES) as u16;
+        let si = self.get_register(Register::SI) as u16;
+        let di = self.get_register(Register::DI) as u16;
+        let (uses_si, uses_di) = match family {
+            StringFamily::Movs => {
+                let value = self.read_memory(source_segment, si, wide);
+                self.write_memory(destination_segment, di, value, wide);
+                (true, true)
+            }
+            StringFamily::Cmps => {
+                let source = self.read_memory(source_segment, si, wide);
+                let destination = self.read_memory(destination_segment, di, wide);
+                self.compute_arithmetic(ArithmeticFamily::Cmp, source, destination, wide);
+                (true, true)
+            }
+            StringFamily::Scas => {
+                let value = self.get_operand_value(&Operand::Register(accumulator), wide);
+                let destination = self.read_memory(destination_segment, di, wide);
+                self.compute_arithmetic(ArithmeticFamily::Cmp, value as u16, destination, wide);
+                (false, true)
+            }
+            StringFamily::Lods => {
+                let value = self.read_memory(source_segment, si, wide);
+                self.set_operand_value(&Operand::Register(accumulator), value as i16, wide);
+                (true, false)
+            }
+            StringFamily::Stos => {
+                let value = self.get_operand_value(&Operand::Register(accumulator), wide);
+                self.write_memory(destination_segment, di, value as u16, wide);
+                (false, true)
+            }
+        };
+        if uses_si {
+            self.set_register(Register::SI, si.wrapping_add(step) as i16);
+        }
+        if uses_di {
+            self.set_register(Register::DI, di.wrapping_add(step) as i16);
+        }
+    }
+
+    /// Multiplies the accumulator by `operand`, into AX for bytes and DX:AX for words. CF and
+    /// OF tell whether the upper half is more than the extension of the lower one.
+    fn multiply(&mut self, signed: bool, operand: &Operand, wide: bool) {
+        let source = self.get_operand_value(operand, wide);
+        let upper_half_used = if wide {
+            let ax = self.get_register(Register::AX);
+            let (product, upper_half_used) = if signed {
+                let product = ax as i32 * source as i32;
+                (product as u32, product != product as i16 as i32)
+            } else {
+                let product = ax as u16 as u32 * source as u16 as u32;
+                (product, product > 0xFFFF)
+            };
+            self.set_register(Register::AX, product as i16);
+            self.set_register(Register::DX, (product >> 16) as i16);
+            upper_half_used
+        } else {
+            let al = self.get_operand_value(&Operand::Register(Register::AL), false);
+            let (product, upper_half_used) = if signed {
+                let product = al * source;
+                (product, product != product as i8 as i16)
+            } else {
+                let product = (al as u8 as u16 * source as u8 as u16) as i16;
+                (product, product as u16 > 0xFF)
+            };
+            self.set_register(Register::AX, product);
+            upper_half_used
+        };
+        self.flags.set_carry(upper_half_used);
+        self.flags.set_overflow(upper_half_used);
+    }
+
+    /// Divides AX, or DX:AX for words, by `operand`, leaving the quotient in AL/AX and the
+    /// remainder in AH/DX. Returns false, changing nothing, when the divisor is zero or the
+    /// quotient doesn't fit.
+    fn divide(&mut self, signed: bool, operand: &Operand, wide: bool) -> bool {
+        let divisor = self.get_operand_value(operand, wide);
+        let ax = self.get_register(Register::AX);
+        let (dividend, divisor, max) = match (wide, signed) {
+            (true, _) => {
+                let dx = self.get_register(Register::DX);
+                let dividend = ((dx as u16 as u32) << 16) | ax as u16 as u32;
+                if signed {
+                    (dividend as i32 as i64, divisor as i64, 0x7FFF)
+                } else {
+                    (dividend as i64, divisor as u16 as i64, 0xFFFF)
+                }
+            }
+            (false, true) => (ax as i64, divisor as i8 as i64, 0x7F),
+            (false, false) => (ax as u16 as i64, divisor as u8 as i64, 0xFF),
+        };
+        if divisor == 0 {
+            return false;
+        }
+        let (quotient, remainder) = (dividend / divisor, dividend % divisor);
+        // The 8086 faults on the most negative quotient as well, the range is symmetric.
+        if quotient > max || quotient < -max {
+            return false;
+        }
+        if wide {
+            self.set_register(Register::AX, quotient as i16);
+            self.set_register(Register::DX, remainder as i16);
+        } else {
+            self.set_register_low(Register::AL, quotient as i8);
+            self.set_register_high(Register::AH, remainder as i8);
+        }
+        true
+    }
+
+    /// Whether the interrupt vector table has a handler for `vector`, a null one meaning no.
+    fn has_interrupt_handler(&self, vector: u8) -> bool {
+        let entry = vector as u16 * 4;
+        self.memory.read_u16(0, entry) != 0 || self.memory.read_u16(0, entry + 2) != 0
+    }
+
+    /// Gives interrupt `vector` to the host hook, returning whether it handled it.
+    fn hook_interrupt(&mut self, vector: u8) -> bool {
+        let Some(mut hook) = self.interrupt_hook.take() else {
+            return false;
+        };
+        let handled = hook.interrupt(self, vector);
+        self.interrupt_hook = Some(hook);
+        handled
+    }
+
+    /// Port of IN and OUT, an immediate byte or DX.
+    fn port(&self, operand: &Operand) -> u16 {
+        match operand {
+            Operand::ImmediateValue(value) => value.unsigned(),
+            _ => self.get_operand_value(operand, true) as u16,
+        }
+    }
+
+    /// Transfers control to the handler of `vector` in the table at 0000:0000, pushing FLAGS,
+    /// CS and IP and clearing IF and TF on the way.
+    fn interrupt(&mut self, vector: u8) {
+        let entry = vector as u16 * 4;
+        self.push(self.flags.get());
+        self.flags.set_interrupt(false);
+        self.flags.set_trap(false);
+        self.push(self.get_register(Register::CS) as u16);
+        self.push(self.instruction_pointer);
+        let segment = self.memory.read_u16(0, entry + 2);
+        self.set_register(Register::CS, segment as i16);
+        self.instruction_pointer = self.memory.read_u16(0, entry);
+    }
+
+    /// Whether a conditional jump is taken. The LOOP family decrements CX on the way, without
+    /// touching the flags.
+    fn jump_condition(&mut self, variant: ConditionalJumpVariant) -> bool {
+        let flags = &self.flags;
+        match variant {
+            ConditionalJumpVariant::JeJz => flags.zero(),
+            ConditionalJumpVariant::JneJnz => !flags.zero(),
+            ConditionalJumpVariant::JlJnge => flags.sign() != flags.overflow(),
+            ConditionalJumpVariant::JnlJge => flags.sign() == flags.overflow(),
+            ConditionalJumpVariant::JleJng => flags.zero() || flags.sign() != flags.overflow(),
+            ConditionalJumpVariant::JnleJg => !flags.zero() && flags.sign() == flags.overflow(),
+            ConditionalJumpVariant::JbJnae => flags.carry(),
+            ConditionalJumpVariant::JnbJae => !flags.carry(),
+            ConditionalJumpVariant::JbeJna => flags.carry() || flags.zero(),
+            ConditionalJumpVariant::JnbeJa => !flags.carry() && !flags.zero(),
+            ConditionalJumpVariant::JpJpe => flags.parity(),
+            ConditionalJumpVariant::JnpJpo => !flags.parity(),
+            ConditionalJumpVariant::Jo => flags.overflow(),
+            ConditionalJumpVariant::Jno => !flags.overflow(),
+            ConditionalJumpVariant::Js => flags.sign(),
+            ConditionalJumpVariant::Jns => !flags.sign(),
+            ConditionalJumpVariant::Jcxz => self.get_register(Register::CX) == 0,
+            ConditionalJumpVariant::Loop
+            | ConditionalJumpVariant::LoopzLoope
+            | ConditionalJumpVariant::LoopnzLoopne => {
+                let count = self.get_register(Register::CX).wrapping_sub(1);
+                self.set_register(Register::CX, count);
+                count != 0
+                    && match variant {
+                        ConditionalJumpVariant::LoopzLoope => self.flags.zero(),
+                        ConditionalJumpVariant::LoopnzLoopne => !self.flags.zero(),
+                        _ => true,
+                    }
+            }
+        }
+    }
+
+    pub fn execute_instruction(&mut self, instruction: &Instruction) -> Result<(), EmulatorError> {
+        let at = self.instruction_pointer;
+        self.instruction_pointer = self
+            .instruction_pointer
+            .wrapping_add(instruction.total_bytes as u16);
+        self.segment_override = instruction.prefixes.segment;
         match &instruction.opcode {
-            Opcode::Move { variant } => {
+            Opcode::Move { .. } => {
                 let source = instruction.source.as_ref().unwrap();
                 let dest = instruction.destination.as_ref().unwrap();
-
-                match variant {
-                    MoveVariant::ImmToReg => match (dest, source) {
-                        (
-                            Operand::Register(reg),
-                            Operand::ImmediateValue(ImmediateValue::SixteenBits(val)),
-                        ) => self.set_register(*reg, *val),
-                        (
-                            Operand::Register(reg),
-                            Operand::ImmediateValue(ImmediateValue::EightBits(val)),
-                        ) => self.set_register_low(*reg, *val),
-                        _ => panic!("This move variant expects a register and an immediate value, but a {:?} and a {:?} were received", dest, source),
-                    },
-                    MoveVariant::RegMemToFromReg => match (dest, source) {
-                        (Operand::Register(dest_reg), Operand::Register(source_reg)) =>
-                            match (dest_reg, source_reg) {
-                                (Register::AH|Register::BH|Register::CH|Register::DH, Register::AH|Register::BH|Register::CH|Register::DH) => {self.set_register_low(*dest_reg, self.get_register_low(*source_reg))}
-                                (Register::AL|Register::BL|Register::CL|Register::DL, Register::AL|Register::BL|Register::CL|Register::DL) => {self.set_register_high(*dest_reg, self.get_register_high(*source_reg))}
-                                (Register::AX|Register::BX|Register::CX|Register::DX|Register::SP|Register::BP|Register::SI|Register::DI, Register::AX|Register::BX|Register::CX|Register::DX|Register::SP|Register::BP|Register::SI|Register::DI) => {self.set_register(*dest_reg, self.get_register(*source_reg))}
-                                _ => todo!("I think there may be cases where you want to move the high part of a register into the low part of another or viceversa... right? Well, I will implement it later if necessary hehe"),
-                            },
-                        _ => todo!()
-                    },
-                    MoveVariant::ImmToRegMem => match (dest, source) {
-                        (Operand::Address(addr), Operand::ImmediateValue(ImmediateValue::SixteenBits(val))) => {
-                            self.store_into_memory(*addr as usize, *val as u16);
-                        },
-                         _ => todo!()
-                    },
-                    _ => todo!(),
-                }
+                let value = self.get_operand_value(source, instruction.wide);
+                self.set_operand_value(dest, value, instruction.wide);
             }
             Opcode::Arithmetic { family, .. } => {
                 let source = instruction.source.as_ref().unwrap();
                 let dest = instruction.destination.as_ref().unwrap();
-                let source_val = self.get_operand_value(source);
-                let dest_val = self.get_operand_value(dest);
-                let result = match family {
-                    ArithmeticFamily::Add => {
-                        let result = dest_val + source_val;
-                        self.flags.sign = (result as u16 & 0x8000) == 0x8000;
-                        self.flags.zero = result == 0;
-                        result
-                    }
-                    ArithmeticFamily::Sub => {
-                        let result = dest_val - source_val;
-                        self.flags.sign = (result as u16 & 0x8000) == 0x8000;
-                        self.flags.zero = result == 0;
-                        result
+                let source_val = self.get_operand_value(source, instruction.wide) as u16;
+                let dest_val = self.get_operand_value(dest, instruction.wide) as u16;
+                let result =
+                    self.compute_arithmetic(*family, dest_val, source_val, instruction.wide);
+                match family {
+                    ArithmeticFamily::Cmp | ArithmeticFamily::Test => {}
+                    _ => self.set_operand_value(dest, result as i16, instruction.wide),
+                }
+            }
+            Opcode::ConditionalJump { variant } => {
+                if self.jump_condition(*variant) {
+                    if let Some(Operand::ImmediateValue(offset)) = &instruction.destination {
+                        self.instruction_pointer = self
+                            .instruction_pointer
+                            .wrapping_add(offset.signed() as u16);
                     }
-                    ArithmeticFamily::Cmp => {
-                        let result = dest_val - source_val;
-                        self.flags.sign = (result as u16 & 0x8000) == 0x8000;
-                        self.flags.zero = result == 0;
-                        dest_val
+                }
+            }
+            Opcode::Push => {
+                let value = match instruction.destination.as_ref().unwrap() {
+                    // The 8086 stores SP as it is once decremented.
+                    Operand::Register(Register::SP) => {
+                        (self.get_register(Register::SP) as u16).wrapping_sub(2)
                     }
+                    operand => self.get_operand_value(operand, true) as u16,
                 };
-                match dest {
-                    Operand::Register(reg) => match reg {
-                        Register::AL | Register::AH | Register::AX => {
-                            self.registers.reg_a.set(result)
-                        }
-                        Register::BL | Register::BH | Register::BX => {
-                            self.registers.reg_b.set(result)
-                        }
-                        Register::CL | Register::CH | Register::CX => {
-                            self.registers.reg_c.set(result)
-                        }
-                        Register::DL | Register::DH | Register::DX => {
-                            self.registers.reg_d.set(result)
+                self.push(value);
+            }
+            Opcode::Pop => {
+                let value = self.pop();
+                let destination = instruction.destination.as_ref().unwrap();
+                self.set_operand_value(destination, value as i16, true);
+            }
+            Opcode::Transfer { family } => match family {
+                TransferFamily::Pushf => self.push(self.flags.get()),
+                TransferFamily::Popf => {
+                    let value = self.pop();
+                    self.flags.set(value)
+                }
+                TransferFamily::Xlat => {
+                    let segment = self.segment_override.unwrap_or(Register::DS);
+                    let segment = self.get_register(segment) as u16;
+                    let [al, _] = (self.get_register(Register::AX) as u16).to_le_bytes();
+                    let offset = (self.get_register(Register::BX) as u16).wrapping_add(al as u16);
+                    let value = self.memory.read_u8(segment, offset);
+                    self.set_register_low(Register::AL, value as i8);
+                }
+                TransferFamily::Lea | TransferFamily::Lds | TransferFamily::Les => {
+                    let register = instruction.destination.as_ref().unwrap();
+                    let address = instruction.source.as_ref().unwrap();
+                    // LEA only computes the offset, the others load the far pointer there.
+                    let offset = match family {
+                        TransferFamily::Lea => self.memory_address(address).unwrap().1,
+                        _ => {
+                            let (segment, offset) = self.far_pointer(address);
+                            let segment_register = if *family == TransferFamily::Lds {
+                                Register::DS
+                            } else {
+                                Register::ES
+                            };
+                            self.set_register(segment_register, segment as i16);
+                            offset
                         }
-                        Register::SI => self.registers.reg_si.set(result),
-                        Register::DI => self.registers.reg_di.set(result),
-                        Register::SP => self.registers.reg_sp.set(result),
-                        Register::BP => self.registers.reg_bp.set(result),
-                    },
-                    _ => todo!(),
+                    };
+                    self.set_operand_value(register, offset as i16, true);
+                }
+                TransferFamily::Lahf => {
+                    self.set_register_high(Register::AH, self.flags.get() as u8 as i8)
+                }
+                TransferFamily::Sahf => {
+                    let [_, ah] = (self.get_register(Register::AX) as u16).to_le_bytes();
+                    self.flags.set((self.flags.get() & 0xFF00) | ah as u16);
                 }
+                TransferFamily::Cbw => {
+                    let [al, _] = (self.get_register(Register::AX) as u16).to_le_bytes();
+                    self.set_register(Register::AX, al as i8 as i16);
+                }
+                TransferFamily::Cwd => {
+                    let negative = self.get_register(Register::AX) < 0;
+                    self.set_register(Register::DX, if negative { -1 } else { 0 });
+                }
+            },
+            Opcode::Exchange => {
+                let dest = instruction.destination.as_ref().unwrap();
+                let source = instruction.source.as_ref().unwrap();
+                let dest_value = self.get_operand_value(dest, instruction.wide);
+                let source_value = self.get_operand_value(source, instruction.wide);
+                self.set_operand_value(dest, source_value, instruction.wide);
+                self.set_operand_value(source, dest_value, instruction.wide);
             }
-            Opcode::ConditionalJump { variant } => {
-                // self.instruction_pointer = ???
+            Opcode::Jump { variant } => {
+                let target = instruction.destination.as_ref().unwrap();
                 match variant {
-                    ConditionalJumpVariant::JeJz => todo!(),
-                    ConditionalJumpVariant::JlJnge => todo!(),
-                    ConditionalJumpVariant::JleJng => todo!(),
-                    ConditionalJumpVariant::JbJnae => todo!(),
-                    ConditionalJumpVariant::JbeJna => todo!(),
-                    ConditionalJumpVariant::JpJpe => todo!(),
-                    ConditionalJumpVariant::Jo => todo!(),
-                    ConditionalJumpVariant::Js => todo!(),
-                    ConditionalJumpVariant::JneJnz => {
-                        let jump_to = instruction.destination.as_ref().unwrap();
-                        if let Operand::ImmediateValue(imm_val) = jump_to {
-                            let offset = match imm_val {
-                                ImmediateValue::EightBits(offset) => *offset as i16,
-                                ImmediateValue::SixteenBits(offset) => *offset as i16,
-                            };
-                            self.instruction_pointer =
-                                ((self.instruction_pointer as i16) + offset) as u16;
+                    JumpVariant::Far | JumpVariant::FarIndirect => {
+                        let (segment, offset) = self.far_pointer(target);
+                        self.set_register(Register::CS, segment as i16);
+                        self.instruction_pointer = offset;
+                    }
+                    JumpVariant::NearIndirect => {
+                        self.instruction_pointer = self.get_operand_value(target, true) as u16;
+                    }
+                    JumpVariant::Short | JumpVariant::Near => {
+                        let offset = self.get_operand_value(target, true) as u16;
+                        self.instruction_pointer = self.instruction_pointer.wrapping_add(offset);
+                    }
+                }
+            }
+            Opcode::Call { variant } => {
+                let target = instruction.destination.as_ref().unwrap();
+                let return_address = self.instruction_pointer;
+                match variant {
+                    JumpVariant::Far | JumpVariant::FarIndirect => {
+                        let (segment, offset) = self.far_pointer(target);
+                        self.push(self.get_register(Register::CS) as u16);
+                        self.set_register(Register::CS, segment as i16);
+                        self.instruction_pointer = offset;
+                    }
+                    JumpVariant::NearIndirect => {
+                        self.instruction_pointer = self.get_operand_value(target, true) as u16;
+                    }
+                    JumpVariant::Short | JumpVariant::Near => {
+                        let offset = self.get_operand_value(target, true) as u16;
+                        self.instruction_pointer = self.instruction_pointer.wrapping_add(offset);
+                    }
+                }
+                self.push(return_address);
+            }
+            Opcode::Return { variant } => {
+                self.instruction_pointer = self.pop();
+                if let ReturnVariant::Far = variant {
+                    let segment = self.pop();
+                    self.set_register(Register::CS, segment as i16);
+                }
+                if let Some(count) = &instruction.destination {
+                    let sp = self.get_register(Register::SP) as u16;
+                    let count = self.get_operand_value(count, true) as u16;
+                    self.set_register(Register::SP, sp.wrapping_add(count) as i16);
+                }
+            }
+            Opcode::Unary { family } => {
+                let operand = instruction.destination.as_ref().unwrap();
+                let wide = instruction.wide;
+                match family {
+                    UnaryFamily::Mul => self.multiply(false, operand, wide),
+                    UnaryFamily::Imul => self.multiply(true, operand, wide),
+                    UnaryFamily::Div | UnaryFamily::Idiv => {
+                        if !self.divide(*family == UnaryFamily::Idiv, operand, wide)
+                            && !self.hook_interrupt(0)
+                        {
+                            if !self.has_interrupt_handler(0) {
+                                return Err(EmulatorError::DivideError {
+                                    cs: self.get_register(Register::CS) as u16,
+                                    ip: at,
+                                });
+                            }
+                            self.interrupt(0);
                         }
                     }
-                    ConditionalJumpVariant::JnlJge => todo!(),
-                    ConditionalJumpVariant::JnleJg => todo!(),
-                    ConditionalJumpVariant::JnbJae => todo!(),
-                    ConditionalJumpVariant::JnbeJa => todo!(),
-                    ConditionalJumpVariant::JnpJpo => todo!(),
-                    ConditionalJumpVariant::Jno => todo!(),
-                    ConditionalJumpVariant::Jns => todo!(),
-                    ConditionalJumpVariant::Loop => todo!(),
-                    ConditionalJumpVariant::LoopzLoope => todo!(),
-                    ConditionalJumpVariant::LoopnzLoopne => todo!(),
-                    ConditionalJumpVariant::Jcxz => todo!(),
+                    UnaryFamily::Inc | UnaryFamily::Dec => {
+                        let family = if *family == UnaryFamily::Inc {
+                            ArithmeticFamily::Add
+                        } else {
+                            ArithmeticFamily::Sub
+                        };
+                        // INC and DEC leave CF alone.
+                        let carry = self.flags.carry();
+                        let value = self.get_operand_value(operand, wide) as u16;
+                        let result = self.compute_arithmetic(family, value, 1, wide);
+                        self.flags.set_carry(carry);
+                        self.set_operand_value(operand, result as i16, wide);
+                    }
+                    UnaryFamily::Neg => {
+                        let value = self.get_operand_value(operand, wide) as u16;
+                        let result = self.compute_arithmetic(ArithmeticFamily::Sub, 0, value, wide);
+                        self.set_operand_value(operand, result as i16, wide);
+                    }
+                    UnaryFamily::Not => {
+                        let value = self.get_operand_value(operand, wide);
+                        self.set_operand_value(operand, !value, wide);
+                    }
+                }
+            }
+            Opcode::Interrupt { variant } => {
+                let vector = match variant {
+                    InterruptVariant::Int => {
+                        let vector = instruction.destination.as_ref().unwrap();
+                        Some(self.get_operand_value(vector, false) as u8)
+                    }
+                    InterruptVariant::Int3 => Some(3),
+                    InterruptVariant::Into => self.flags.overflow().then_some(4),
+                    InterruptVariant::Iret => {
+                        self.instruction_pointer = self.pop();
+                        let segment = self.pop();
+                        self.set_register(Register::CS, segment as i16);
+                        let flags = self.pop();
+                        self.flags.set(flags);
+                        None
+                    }
+                };
+                if let Some(vector) = vector {
+                    if !self.hook_interrupt(vector) {
+                        self.interrupt(vector);
+                    }
+                }
+            }
+            Opcode::Input => {
+                let port = self.port(instruction.source.as_ref().unwrap());
+                let value = if instruction.wide {
+                    self.io_bus.read_u16(port)
+                } else {
+                    self.io_bus.read_u8(port) as u16
+                };
+                let dest = instruction.destination.as_ref().unwrap();
+                self.set_operand_value(dest, value as i16, instruction.wide);
+            }
+            Opcode::Output => {
+                let port = self.port(instruction.destination.as_ref().unwrap());
+                let source = instruction.source.as_ref().unwrap();
+                let value = self.get_operand_value(source, instruction.wide) as u16;
+                if instruction.wide {
+                    self.io_bus.write_u16(port, value);
+                } else {
+                    self.io_bus.write_u8(port, value as u8);
                 }
             }
-            _ => todo!(),
+            Opcode::Adjust { family } => self.adjust(*family),
+            Opcode::Shift { family } => {
+                let dest = instruction.destination.as_ref().unwrap();
+                let count = self.get_operand_value(instruction.source.as_ref().unwrap(), false);
+                let value = self.get_operand_value(dest, instruction.wide) as u16;
+                let result = self.compute_shift(*family, value, count as u8, instruction.wide);
+                self.set_operand_value(dest, result as i16, instruction.wide);
+            }
+            Opcode::String { family } => {
+                self.execute_string(*family, instruction.wide, instruction.prefixes.repeat)
+            }
+            Opcode::Processor { family } => match family {
+                ProcessorFamily::Clc => self.flags.set_carry(false),
+                ProcessorFamily::Cmc => self.flags.set_carry(!self.flags.carry()),
+                ProcessorFamily::Stc => self.flags.set_carry(true),
+                ProcessorFamily::Cld => self.flags.set_direction(false),
+                ProcessorFamily::Std => self.flags.set_direction(true),
+                ProcessorFamily::Cli => self.flags.set_interrupt(false),
+                ProcessorFamily::Sti => self.flags.set_interrupt(true),
+                ProcessorFamily::Hlt => self.terminate(Termination::Halted),
+                // There is no coprocessor to wait for.
+                ProcessorFamily::Wait => {}
+            },
+            // Nothing is attached to take the coprocessor instruction.
+            Opcode::Escape => {}
+            Opcode::Prefix { .. } => unreachable!("prefixes are folded into the instruction"),
         };
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorError {
+    /// A division by zero, or with a quotient too large, at `cs:ip` while nothing handles
+    /// interrupt 0.
+    DivideError { cs: u16, ip: u16 },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorError::DivideError { cs, ip } => {
+                write!(f, "divide error at {:04x}:{:04x}", cs, ip)
+            }
+        }
+    }
+}
+
+impl Error for EmulatorError {}
+
 #[derive(Debug)]
 pub struct EmulatorRegisters {
     reg_a: GeneralRegister,
@@ -278,6 +1052,16 @@ pub struct EmulatorRegisters {
     reg_bp: SpecialRegister,
     reg_si: SpecialRegister,
     reg_di: SpecialRegister,
+    reg_es: SpecialRegister,
+    reg_cs: SpecialRegister,
+    reg_ss: SpecialRegister,
+    reg_ds: SpecialRegister,
+}
+
+impl Default for EmulatorRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EmulatorRegisters {
@@ -291,21 +1075,10 @@ impl EmulatorRegisters {
             reg_bp: SpecialRegister(0),
             reg_si: SpecialRegister(0),
             reg_di: SpecialRegister(0),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct EmulatorFlags {
-    zero: bool,
-    sign: bool,
-}
-
-impl EmulatorFlags {
-    fn new() -> Self {
-        Self {
-            zero: false,
-            sign: false,
+            reg_es: SpecialRegister(0),
+            reg_cs: SpecialRegister(0),
+            reg_ss: SpecialRegister(0),
+            reg_ds: SpecialRegister(0),
         }
     }
 }
@@ -314,19 +1087,19 @@ impl EmulatorFlags {
 pub struct GeneralRegister(i16);
 impl GeneralRegister {
     pub fn get_high(&self) -> i8 {
-        ((self.0 as u16 & 0xFF00) >> 4) as i8
+        ((self.0 as u16 & 0xFF00) >> 8) as i8
     }
     pub fn get_low(&self) -> i8 {
-        ((self.0 as u16 & 0x00FF) >> 4) as i8
+        (self.0 as u16 & 0x00FF) as i8
     }
     pub fn get(&self) -> i16 {
         self.0
     }
     pub fn set_high(&mut self, value: i8) {
-        self.0 = (((value as u16) << 8) | ((self.0 as u16) & 0x00FF)) as i16
+        self.0 = (((value as u8 as u16) << 8) | ((self.0 as u16) & 0x00FF)) as i16
     }
     pub fn set_low(&mut self, value: i8) {
-        self.0 = ((value as u16) | ((self.0 as u16) & 0xFF00)) as i16
+        self.0 = ((value as u8 as u16) | ((self.0 as u16) & 0xFF00)) as i16
     }
     pub fn set(&mut self, value: i16) {
         self.0 = value
@@ -344,16 +1117,142 @@ impl SpecialRegister {
     }
 }
 
-#[derive(Debug)]
+/// The 16-bit FLAGS register, each flag a bit at its 8086 position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FlagsRegister(u16);
 impl FlagsRegister {
-    fn zero(&self) -> bool {
-        false
+    pub const CARRY: u16 = 1 << 0;
+    pub const PARITY: u16 = 1 << 2;
+    pub const AUXILIARY_CARRY: u16 = 1 << 4;
+    pub const ZERO: u16 = 1 << 6;
+    pub const SIGN: u16 = 1 << 7;
+    pub const TRAP: u16 = 1 << 8;
+    pub const INTERRUPT: u16 = 1 << 9;
+    pub const DIRECTION: u16 = 1 << 10;
+    pub const OVERFLOW: u16 = 1 << 11;
+    const DEFINED: u16 = Self::CARRY
+        | Self::PARITY
+        | Self::AUXILIARY_CARRY
+        | Self::ZERO
+        | Self::SIGN
+        | Self::TRAP
+        | Self::INTERRUPT
+        | Self::DIRECTION
+        | Self::OVERFLOW;
+    /// Bits the 8086 always reads as set: bit 1 and the four top ones.
+    const RESERVED: u16 = 0xF002;
+
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// The packed value as PUSHF would store it.
+    pub fn get(&self) -> u16 {
+        self.0 | Self::RESERVED
+    }
+    pub fn set(&mut self, value: u16) {
+        self.0 = value & Self::DEFINED
+    }
+
+    fn is_set(&self, flag: u16) -> bool {
+        self.0 & flag != 0
+    }
+    fn set_flag(&mut self, flag: u16, value: bool) {
+        if value {
+            self.0 |= flag
+        } else {
+            self.0 &= !flag
+        }
+    }
+
+    pub fn carry(&self) -> bool {
+        self.is_set(Self::CARRY)
+    }
+    pub fn set_carry(&mut self, value: bool) {
+        self.set_flag(Self::CARRY, value)
+    }
+
+    pub fn parity(&self) -> bool {
+        self.is_set(Self::PARITY)
+    }
+    pub fn set_parity(&mut self, value: bool) {
+        self.set_flag(Self::PARITY, value)
+    }
+
+    pub fn auxiliary_carry(&self) -> bool {
+        self.is_set(Self::AUXILIARY_CARRY)
+    }
+    pub fn set_auxiliary_carry(&mut self, value: bool) {
+        self.set_flag(Self::AUXILIARY_CARRY, value)
+    }
+
+    pub fn zero(&self) -> bool {
+        self.is_set(Self::ZERO)
+    }
+    pub fn set_zero(&mut self, value: bool) {
+        self.set_flag(Self::ZERO, value)
+    }
+
+    pub fn sign(&self) -> bool {
+        self.is_set(Self::SIGN)
+    }
+    pub fn set_sign(&mut self, value: bool) {
+        self.set_flag(Self::SIGN, value)
+    }
+
+    pub fn trap(&self) -> bool {
+        self.is_set(Self::TRAP)
+    }
+    pub fn set_trap(&mut self, value: bool) {
+        self.set_flag(Self::TRAP, value)
     }
-    fn set_zero(&mut self, value: bool) {}
 
-    fn negative(&self) -> bool {
-        false
+    pub fn interrupt(&self) -> bool {
+        self.is_set(Self::INTERRUPT)
+    }
+    pub fn set_interrupt(&mut self, value: bool) {
+        self.set_flag(Self::INTERRUPT, value)
+    }
+
+    pub fn direction(&self) -> bool {
+        self.is_set(Self::DIRECTION)
+    }
+    pub fn set_direction(&mut self, value: bool) {
+        self.set_flag(Self::DIRECTION, value)
+    }
+
+    pub fn overflow(&self) -> bool {
+        self.is_set(Self::OVERFLOW)
+    }
+    pub fn set_overflow(&mut self, value: bool) {
+        self.set_flag(Self::OVERFLOW, value)
+    }
+}
+
+impl Default for FlagsRegister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Letters of the set flags in bit order, the conventional `CPAZSO` plus `T`, `I` and `D`.
+impl fmt::Display for FlagsRegister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, letter) in [
+            (Self::CARRY, "C"),
+            (Self::PARITY, "P"),
+            (Self::AUXILIARY_CARRY, "A"),
+            (Self::ZERO, "Z"),
+            (Self::SIGN, "S"),
+            (Self::TRAP, "T"),
+            (Self::INTERRUPT, "I"),
+            (Self::DIRECTION, "D"),
+            (Self::OVERFLOW, "O"),
+        ] {
+            if self.is_set(flag) {
+                f.write_str(letter)?;
+            }
+        }
+        Ok(())
     }
-    fn set_negative(&mut self, value: bool) {}
 }
@@ -0,0 +1,248 @@
+use core::fmt;
+use std::error::Error;
+
+use super::{emu::Emulator, opc::mov::Register};
+
+/// Segment of the PSP of the program, with room below it for the vector table and DOS itself.
+pub const LOAD_SEGMENT: u16 = 0x1000;
+
+/// The segment past the conventional 640 KiB, the end of the memory a program can be given.
+const MEMORY_TOP: u16 = 0xA000;
+
+/// Size of the Program Segment Prefix, where a .COM image starts.
+const PSP_SIZE: u16 = 0x100;
+
+/// Offset of the command tail in the PSP: its length, the text and a carriage return.
+const COMMAND_TAIL: u16 = 0x80;
+const MAX_COMMAND_TAIL: usize = 126;
+
+/// The .COM image and the stack word have to fit in the one segment.
+const MAX_COM_SIZE: usize = 0x10000 - PSP_SIZE as usize - 2;
+
+/// "MZ", and the "ZM" early linkers also wrote.
+const MZ_SIGNATURES: [u16; 2] = [0x5A4D, 0x4D5A];
+/// The fixed part of the MZ header, up to the overlay number.
+const MZ_HEADER_SIZE: usize = 0x1C;
+const PAGE_SIZE: usize = 512;
+const PARAGRAPH_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The image is `size` bytes, more than the `limit` it can take.
+    ImageTooLarge { size: usize, limit: usize },
+    /// The arguments take `length` bytes, more than the PSP has room for.
+    CommandTailTooLong { length: usize },
+    /// An .EXE without the MZ signature, `found` in its place.
+    BadSignature { found: u16 },
+    /// The file is `size` bytes, the header says there are `expected`.
+    Truncated { size: usize, expected: usize },
+    /// The program needs `needed` paragraphs, only `available` are free.
+    InsufficientMemory { needed: u32, available: u32 },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::ImageTooLarge { size, limit } => {
+                write!(f, "image of {} bytes is larger than {} bytes", size, limit)
+            }
+            LoadError::CommandTailTooLong { length } => write!(
+                f,
+                "command tail of {} bytes is longer than {} bytes",
+                length, MAX_COMMAND_TAIL
+            ),
+            LoadError::BadSignature { found } => {
+                write!(f, "bad MZ signature {:#06x}", found)
+            }
+            LoadError::Truncated { size, expected } => write!(
+                f,
+                "file of {} bytes is truncated, expected {} bytes",
+                size, expected
+            ),
+            LoadError::InsufficientMemory { needed, available } => write!(
+                f,
+                "program needs {} paragraphs, only {} are available",
+                needed, available
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// Loads an .EXE or a .COM program, told apart by the MZ signature like DOS does.
+pub fn load(
+    emulator: &mut Emulator,
+    segment: u16,
+    file: &[u8],
+    args: &[String],
+) -> Result<(), LoadError> {
+    match file {
+        [first, second, ..] if MZ_SIGNATURES.contains(&u16::from_le_bytes([*first, *second])) => {
+            load_exe(emulator, segment, file, args)
+        }
+        _ => load_com(emulator, segment, file, args),
+    }
+}
+
+/// Loads a .COM program like DOS does: the PSP at `segment:0000`, the image right after it at
+/// `segment:0100` where it starts, every segment register at `segment` and the stack at the end
+/// of it, with a zero on it so a RET goes to the INT 20h at the start of the PSP.
+pub fn load_com(
+    emulator: &mut Emulator,
+    segment: u16,
+    image: &[u8],
+    args: &[String],
+) -> Result<(), LoadError> {
+    if image.len() > MAX_COM_SIZE {
+        return Err(LoadError::ImageTooLarge {
+            size: image.len(),
+            limit: MAX_COM_SIZE,
+        });
+    }
+    write_psp(emulator, segment, args)?;
+    let memory = emulator.memory_mut();
+    memory.load(segment, PSP_SIZE, image);
+    memory.write_u16(segment, 0xFFFE, 0);
+    for register in [Register::CS, Register::DS, Register::ES, Register::SS] {
+        emulator.set_register_value(register, segment);
+    }
+    emulator.set_register_value(Register::SP, 0xFFFE);
+    emulator.instruction_pointer = PSP_SIZE;
+    Ok(())
+}
+
+/// Loads an MZ .EXE program like DOS does: the PSP at `segment:0000`, the image in the
+/// paragraphs after it with the relocations applied, CS:IP and SS:SP from the header, and DS
+/// and ES at the PSP. The program gets the memory it asks for, between its minimum and maximum
+/// allocation.
+pub fn load_exe(
+    emulator: &mut Emulator,
+    segment: u16,
+    file: &[u8],
+    args: &[String],
+) -> Result<(), LoadError> {
+    let header = MzHeader::parse(file)?;
+    let image = &file[header.image_start..header.image_end];
+    let load_segment = segment.wrapping_add((PSP_SIZE as usize / PARAGRAPH_SIZE) as u16);
+    let image_paragraphs = image.len().div_ceil(PARAGRAPH_SIZE) as u32;
+    let available = (MEMORY_TOP as u32).saturating_sub(load_segment as u32);
+    let needed = image_paragraphs + header.min_alloc as u32;
+    if needed > available {
+        return Err(LoadError::InsufficientMemory { needed, available });
+    }
+    let extra = (header.max_alloc.max(header.min_alloc) as u32).min(available - image_paragraphs);
+    write_psp(emulator, segment, args)?;
+    let memory = emulator.memory_mut();
+    memory.write_u16(
+        segment,
+        2,
+        (load_segment as u32 + image_paragraphs + extra) as u16,
+    );
+    // An image can be larger than a segment, each 64 KiB of it goes in the next one.
+    for (i, chunk) in image.chunks(0x10000).enumerate() {
+        memory.load(load_segment.wrapping_add(i as u16 * 0x1000), 0, chunk);
+    }
+    for (offset, relative_segment) in header.relocations(file) {
+        let fixup_segment = load_segment.wrapping_add(relative_segment);
+        let value = memory.read_u16(fixup_segment, offset);
+        memory.write_u16(fixup_segment, offset, value.wrapping_add(load_segment));
+    }
+    for (register, value) in [
+        (Register::CS, load_segment.wrapping_add(header.cs)),
+        (Register::SS, load_segment.wrapping_add(header.ss)),
+        (Register::DS, segment),
+        (Register::ES, segment),
+        (Register::SP, header.sp),
+    ] {
+        emulator.set_register_value(register, value);
+    }
+    emulator.instruction_pointer = header.ip;
+    Ok(())
+}
+
+/// The fields of the MZ header the loader needs, checked against the size of the file.
+struct MzHeader {
+    image_start: usize,
+    image_end: usize,
+    relocation_count: usize,
+    relocation_table: usize,
+    min_alloc: u16,
+    max_alloc: u16,
+    ss: u16,
+    sp: u16,
+    ip: u16,
+    cs: u16,
+}
+
+impl MzHeader {
+    fn parse(file: &[u8]) -> Result<Self, LoadError> {
+        if file.len() < MZ_HEADER_SIZE {
+            return Err(LoadError::Truncated {
+                size: file.len(),
+                expected: MZ_HEADER_SIZE,
+            });
+        }
+        let word = |offset: usize| u16::from_le_bytes([file[offset], file[offset + 1]]);
+        if !MZ_SIGNATURES.contains(&word(0x00)) {
+            return Err(LoadError::BadSignature { found: word(0x00) });
+        }
+        // The last page is only partly used, unless the count of its bytes is 0.
+        let pages = word(0x04) as usize;
+        let image_end = match word(0x02) as usize % PAGE_SIZE {
+            0 => pages * PAGE_SIZE,
+            last_page => pages.saturating_sub(1) * PAGE_SIZE + last_page,
+        };
+        let image_start = word(0x08) as usize * PARAGRAPH_SIZE;
+        let header = MzHeader {
+            image_start,
+            // A header claiming more than the whole file leaves no image.
+            image_end: image_end.max(image_start),
+            relocation_count: word(0x06) as usize,
+            relocation_table: word(0x18) as usize,
+            min_alloc: word(0x0A),
+            max_alloc: word(0x0C),
+            ss: word(0x0E),
+            sp: word(0x10),
+            ip: word(0x14),
+            cs: word(0x16),
+        };
+        let expected = header
+            .image_end
+            .max(header.relocation_table + 4 * header.relocation_count);
+        if file.len() < expected {
+            return Err(LoadError::Truncated {
+                size: file.len(),
+                expected,
+            });
+        }
+        Ok(header)
+    }
+
+    /// Offsets and segments, relative to the image, of the words to add the load segment to.
+    fn relocations<'a>(&self, file: &'a [u8]) -> impl Iterator<Item = (u16, u16)> + 'a {
+        let table = &file[self.relocation_table..][..4 * self.relocation_count];
+        table.chunks_exact(4).map(|entry| {
+            (
+                u16::from_le_bytes([entry[0], entry[1]]),
+                u16::from_le_bytes([entry[2], entry[3]]),
+            )
+        })
+    }
+}
+
+/// The parts of the PSP programs look at: the INT 20h to return to, the end of their memory
+/// and the command tail, the arguments each after a space.
+fn write_psp(emulator: &mut Emulator, segment: u16, args: &[String]) -> Result<(), LoadError> {
+    let tail: String = args.iter().map(|arg| format!(" {}", arg)).collect();
+    if tail.len() > MAX_COMMAND_TAIL {
+        return Err(LoadError::CommandTailTooLong { length: tail.len() });
+    }
+    let memory = emulator.memory_mut();
+    memory.load(segment, 0, &[0xCD, 0x20]);
+    memory.write_u16(segment, 2, MEMORY_TOP);
+    memory.write_u8(segment, COMMAND_TAIL, tail.len() as u8);
+    memory.load(segment, COMMAND_TAIL + 1, tail.as_bytes());
+    memory.write_u8(segment, COMMAND_TAIL + 1 + tail.len() as u16, b'\r');
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use core::fmt;
+
+/// The 1 MiB the 20 address lines of the 8086 reach.
+const SIZE: usize = 0x100000;
+
+/// The memory of the emulated machine, addressed as segment:offset. Words are little-endian
+/// and a word at the last offset of a segment wraps around to its start, as on the 8086.
+pub struct Memory {
+    bytes: Box<[u8]>,
+}
+
+/// Physical address of `segment:offset`, wrapping past 1 MiB like the 8086 does.
+pub fn physical_address(segment: u16, offset: u16) -> usize {
+    (((segment as usize) << 4) + offset as usize) & (SIZE - 1)
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory {
+            bytes: vec![0; SIZE].into_boxed_slice(),
+        }
+    }
+
+    pub fn read_u8(&self, segment: u16, offset: u16) -> u8 {
+        self.bytes[physical_address(segment, offset)]
+    }
+
+    pub fn read_u16(&self, segment: u16, offset: u16) -> u16 {
+        u16::from_le_bytes([
+            self.read_u8(segment, offset),
+            self.read_u8(segment, offset.wrapping_add(1)),
+        ])
+    }
+
+    pub fn write_u8(&mut self, segment: u16, offset: u16, value: u8) {
+        self.bytes[physical_address(segment, offset)] = value
+    }
+
+    pub fn write_u16(&mut self, segment: u16, offset: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_u8(segment, offset, low);
+        self.write_u8(segment, offset.wrapping_add(1), high);
+    }
+
+    /// Copies `bytes` to `segment:offset` on, the offset wrapping within the segment.
+    pub fn load(&mut self, segment: u16, offset: u16, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write_u8(segment, offset.wrapping_add(i as u16), *byte);
+        }
+    }
+
+    /// Physical addresses and values of the bytes that are not zero.
+    pub fn non_zero(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte != 0)
+            .map(|(address, byte)| (address, *byte))
+    }
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.non_zero()).finish()
+    }
+}
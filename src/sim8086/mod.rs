@@ -1,3 +1,7 @@
+pub mod bus;
 pub mod dis;
+pub mod dos;
 pub mod emu;
-mod opc;
+pub mod load;
+pub mod mem;
+pub mod opc;
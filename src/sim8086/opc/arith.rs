@@ -1,13 +1,87 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArithmeticFamily {
     Add,
+    Or,
+    Adc,
+    Sbb,
+    And,
     Sub,
+    Xor,
     Cmp,
+    Test,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArithmeticVariant {
     RegMemAndRegEither,
     ImmRegMem,
     ImmAcc,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryFamily {
+    Inc,
+    Dec,
+    Neg,
+    Not,
+    Mul,
+    Imul,
+    Div,
+    Idiv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjustFamily {
+    Daa,
+    Das,
+    Aaa,
+    Aas,
+    Aam,
+    Aad,
+}
+
+impl fmt::Display for ArithmeticFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArithmeticFamily::Add => "add",
+            ArithmeticFamily::Or => "or",
+            ArithmeticFamily::Adc => "adc",
+            ArithmeticFamily::Sbb => "sbb",
+            ArithmeticFamily::And => "and",
+            ArithmeticFamily::Sub => "sub",
+            ArithmeticFamily::Xor => "xor",
+            ArithmeticFamily::Cmp => "cmp",
+            ArithmeticFamily::Test => "test",
+        })
+    }
+}
+
+impl fmt::Display for UnaryFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnaryFamily::Inc => "inc",
+            UnaryFamily::Dec => "dec",
+            UnaryFamily::Neg => "neg",
+            UnaryFamily::Not => "not",
+            UnaryFamily::Mul => "mul",
+            UnaryFamily::Imul => "imul",
+            UnaryFamily::Div => "div",
+            UnaryFamily::Idiv => "idiv",
+        })
+    }
+}
+
+impl fmt::Display for AdjustFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AdjustFamily::Daa => "daa",
+            AdjustFamily::Das => "das",
+            AdjustFamily::Aaa => "aaa",
+            AdjustFamily::Aas => "aas",
+            AdjustFamily::Aam => "aam",
+            AdjustFamily::Aad => "aad",
+        })
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConditionalJumpVariant {
     JeJz,
     JlJnge,
@@ -21,3 +23,30 @@ pub enum ConditionalJumpVariant {
     LoopnzLoopne,
     Jcxz,
 }
+
+impl fmt::Display for ConditionalJumpVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConditionalJumpVariant::JeJz => "je",
+            ConditionalJumpVariant::JlJnge => "jl",
+            ConditionalJumpVariant::JleJng => "jle",
+            ConditionalJumpVariant::JbJnae => "jb",
+            ConditionalJumpVariant::JbeJna => "jbe",
+            ConditionalJumpVariant::JpJpe => "jp",
+            ConditionalJumpVariant::Jo => "jo",
+            ConditionalJumpVariant::Js => "js",
+            ConditionalJumpVariant::JneJnz => "jne",
+            ConditionalJumpVariant::JnlJge => "jnl",
+            ConditionalJumpVariant::JnleJg => "jnle",
+            ConditionalJumpVariant::JnbJae => "jnb",
+            ConditionalJumpVariant::JnbeJa => "jnbe",
+            ConditionalJumpVariant::JnpJpo => "jnp",
+            ConditionalJumpVariant::Jno => "jno",
+            ConditionalJumpVariant::Jns => "jns",
+            ConditionalJumpVariant::Loop => "loop",
+            ConditionalJumpVariant::LoopzLoope => "loopz",
+            ConditionalJumpVariant::LoopnzLoopne => "loopnz",
+            ConditionalJumpVariant::Jcxz => "jcxz",
+        })
+    }
+}
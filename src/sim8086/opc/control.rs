@@ -0,0 +1,51 @@
+use std::fmt;
+
+use super::mov::Register;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessorFamily {
+    Clc,
+    Cmc,
+    Stc,
+    Cld,
+    Std,
+    Cli,
+    Sti,
+    Hlt,
+    Wait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixVariant {
+    Lock,
+    Rep,
+    Repne,
+    Segment(Register),
+}
+
+impl fmt::Display for ProcessorFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProcessorFamily::Clc => "clc",
+            ProcessorFamily::Cmc => "cmc",
+            ProcessorFamily::Stc => "stc",
+            ProcessorFamily::Cld => "cld",
+            ProcessorFamily::Std => "std",
+            ProcessorFamily::Cli => "cli",
+            ProcessorFamily::Sti => "sti",
+            ProcessorFamily::Hlt => "hlt",
+            ProcessorFamily::Wait => "wait",
+        })
+    }
+}
+
+impl fmt::Display for PrefixVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefixVariant::Lock => f.write_str("lock"),
+            PrefixVariant::Rep => f.write_str("rep"),
+            PrefixVariant::Repne => f.write_str("repne"),
+            PrefixVariant::Segment(register) => write!(f, "{}", register),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Shared by JMP and CALL, CALL has no short form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpVariant {
+    Short,
+    Near,
+    Far,
+    NearIndirect,
+    FarIndirect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnVariant {
+    Near,
+    Far,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptVariant {
+    Int,
+    Int3,
+    Into,
+    Iret,
+}
+
+impl fmt::Display for ReturnVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReturnVariant::Near => "ret",
+            ReturnVariant::Far => "retf",
+        })
+    }
+}
+
+impl fmt::Display for InterruptVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InterruptVariant::Int => "int",
+            InterruptVariant::Int3 => "int3",
+            InterruptVariant::Into => "into",
+            InterruptVariant::Iret => "iret",
+        })
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftFamily {
+    Rol,
+    Ror,
+    Rcl,
+    Rcr,
+    ShlSal,
+    Shr,
+    Sar,
+}
+
+impl fmt::Display for ShiftFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShiftFamily::Rol => "rol",
+            ShiftFamily::Ror => "ror",
+            ShiftFamily::Rcl => "rcl",
+            ShiftFamily::Rcr => "rcr",
+            ShiftFamily::ShlSal => "shl",
+            ShiftFamily::Shr => "shr",
+            ShiftFamily::Sar => "sar",
+        })
+    }
+}
@@ -1,166 +1,215 @@
 pub mod arith;
 pub mod cond_jump;
+pub mod control;
+pub mod jump;
+pub mod logic;
 pub mod mov;
+pub mod string;
+pub mod table;
+pub mod transfer;
 
-use arith::{ArithmeticFamily, ArithmeticVariant};
+use std::fmt;
+
+use arith::{AdjustFamily, ArithmeticFamily, ArithmeticVariant, UnaryFamily};
 use cond_jump::ConditionalJumpVariant;
+use control::{PrefixVariant, ProcessorFamily};
+use jump::{InterruptVariant, JumpVariant, ReturnVariant};
+use logic::ShiftFamily;
+use string::StringFamily;
+use table::{Encoding, Field, ENCODINGS};
+use transfer::TransferFamily;
 
 use self::mov::MoveVariant;
+use super::dis::DecodeError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     Move {
         variant: MoveVariant,
     },
+    Push,
+    Pop,
+    Exchange,
+    Input,
+    Output,
+    Transfer {
+        family: TransferFamily,
+    },
     Arithmetic {
         variant: ArithmeticVariant,
         family: ArithmeticFamily,
     },
+    Unary {
+        family: UnaryFamily,
+    },
+    Adjust {
+        family: AdjustFamily,
+    },
+    Shift {
+        family: ShiftFamily,
+    },
+    String {
+        family: StringFamily,
+    },
     ConditionalJump {
         variant: ConditionalJumpVariant,
     },
-    NotImplemented,
+    Jump {
+        variant: JumpVariant,
+    },
+    Call {
+        variant: JumpVariant,
+    },
+    Return {
+        variant: ReturnVariant,
+    },
+    Interrupt {
+        variant: InterruptVariant,
+    },
+    Processor {
+        family: ProcessorFamily,
+    },
+    Escape,
+    Prefix {
+        variant: PrefixVariant,
+    },
 }
 
-pub fn parse_opcode(first_byte: u8, second_byte: u8) -> Opcode {
-    if let Some(variant) = try_decode_move(first_byte) {
-        Opcode::Move { variant }
-    } else if let Some((family, variant)) = try_decode_arithmetic(first_byte, second_byte) {
-        Opcode::Arithmetic { family, variant }
-    } else if let Some(variant) = try_decode_jump(first_byte) {
-        Opcode::ConditionalJump { variant }
-    } else {
-        Opcode::NotImplemented
-    }
+/// Values of the bit fields of an encoding, plus which byte fields follow them.
+#[derive(Debug, Default)]
+pub struct DecodedFields {
+    pub d: u8,
+    pub w: u8,
+    pub s: u8,
+    pub v: Option<u8>,
+    pub mode: Option<u8>,
+    pub reg: Option<u8>,
+    pub rm: u8,
+    pub sr: Option<u8>,
+    pub esc: Option<u8>,
+    pub data: bool,
+    pub data_if_w: bool,
+    pub relative_disp: bool,
+    pub pointer: bool,
+    pub port_dx: bool,
+    /// Bytes taken by the bit fields, displacement and data come after them.
+    pub length: u8,
 }
 
-fn try_decode_move(byte: u8) -> Option<MoveVariant> {
-    if byte >> 2 == 0b100010 {
-        return Some(MoveVariant::RegMemToFromReg);
-    }
-    if byte >> 1 == 0b1100011 {
-        return Some(MoveVariant::ImmToRegMem);
-    }
-    if byte >> 4 == 0b1011 {
-        return Some(MoveVariant::ImmToReg);
-    }
-    if byte >> 1 == 0b1010000 {
-        return Some(MoveVariant::MemToAcc);
+impl Opcode {
+    /// Whether the r/m operand has to be in memory, mod = 11 is meaningless for these.
+    pub fn requires_memory_operand(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Transfer {
+                family: TransferFamily::Lea | TransferFamily::Lds | TransferFamily::Les,
+            } | Opcode::Jump {
+                variant: JumpVariant::FarIndirect,
+            } | Opcode::Call {
+                variant: JumpVariant::FarIndirect,
+            }
+        )
     }
-    if byte >> 1 == 0b1010001 {
-        return Some(MoveVariant::AccToMem);
-    }
-    // if byte == 0b10001110 {
-    //     return Some(MoveVariant::RegMemToSeg);
-    // }
-    // if byte == 10001100 {
-    //     return Some(MoveVariant::SegToRegMem);
-    // }
-    None
 }
 
-fn try_decode_arithmetic(
-    first_byte: u8,
-    second_byte: u8,
-) -> Option<(ArithmeticFamily, ArithmeticVariant)> {
-    if first_byte >> 2 == 0b000000 {
-        return Some((ArithmeticFamily::Add, ArithmeticVariant::RegMemAndRegEither));
-    }
-    if first_byte >> 2 == 0b100000 {
-        return Some((
-            match (second_byte >> 3) & 0b111 {
-                0b000 => ArithmeticFamily::Add, // add
-                0b101 => ArithmeticFamily::Sub, // sub
-                0b111 => ArithmeticFamily::Cmp, // cmp
-                _ => todo!(),
-            },
-            ArithmeticVariant::ImmRegMem,
-        ));
-    }
-    if first_byte >> 1 == 0b0000010 {
-        return Some((ArithmeticFamily::Add, ArithmeticVariant::ImmAcc));
-    }
-    if first_byte >> 2 == 0b001010 {
-        return Some((ArithmeticFamily::Sub, ArithmeticVariant::RegMemAndRegEither));
-    }
-    if first_byte >> 2 == 0b100000 {
-        return Some((ArithmeticFamily::Sub, ArithmeticVariant::ImmRegMem));
-    }
-    if first_byte >> 1 == 0b0010110 {
-        return Some((ArithmeticFamily::Sub, ArithmeticVariant::ImmAcc));
-    }
-    if first_byte >> 2 == 0b001110 {
-        return Some((ArithmeticFamily::Cmp, ArithmeticVariant::RegMemAndRegEither));
-    }
-    if first_byte >> 2 == 0b100000 {
-        return Some((ArithmeticFamily::Cmp, ArithmeticVariant::ImmRegMem));
-    }
-    if first_byte >> 1 == 0b0011110 {
-        return Some((ArithmeticFamily::Cmp, ArithmeticVariant::ImmAcc));
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Move { variant } => write!(f, "{}", variant),
+            Opcode::Push => f.write_str("push"),
+            Opcode::Pop => f.write_str("pop"),
+            Opcode::Exchange => f.write_str("xchg"),
+            Opcode::Input => f.write_str("in"),
+            Opcode::Output => f.write_str("out"),
+            Opcode::Transfer { family } => write!(f, "{}", family),
+            Opcode::Arithmetic { family, .. } => write!(f, "{}", family),
+            Opcode::Unary { family } => write!(f, "{}", family),
+            Opcode::Adjust { family } => write!(f, "{}", family),
+            Opcode::Shift { family } => write!(f, "{}", family),
+            Opcode::String { family } => write!(f, "{}", family),
+            Opcode::ConditionalJump { variant } => write!(f, "{}", variant),
+            Opcode::Jump { .. } => f.write_str("jmp"),
+            Opcode::Call { .. } => f.write_str("call"),
+            Opcode::Return { variant } => write!(f, "{}", variant),
+            Opcode::Interrupt { variant } => write!(f, "{}", variant),
+            Opcode::Processor { family } => write!(f, "{}", family),
+            Opcode::Escape => f.write_str("esc"),
+            Opcode::Prefix { variant } => write!(f, "{}", variant),
+        }
     }
-    None
 }
 
-fn try_decode_jump(byte: u8) -> Option<ConditionalJumpVariant> {
-    if byte == 0b01110100 {
-        return Some(ConditionalJumpVariant::JeJz);
-    }
-    if byte == 0b01111100 {
-        return Some(ConditionalJumpVariant::JlJnge);
-    }
-    if byte == 0b01111110 {
-        return Some(ConditionalJumpVariant::JleJng);
-    }
-    if byte == 0b01110010 {
-        return Some(ConditionalJumpVariant::JbJnae);
-    }
-    if byte == 0b01110110 {
-        return Some(ConditionalJumpVariant::JbeJna);
-    }
-    if byte == 0b01111010 {
-        return Some(ConditionalJumpVariant::JpJpe);
-    }
-    if byte == 0b01110000 {
-        return Some(ConditionalJumpVariant::Jo);
-    }
-    if byte == 0b01111000 {
-        return Some(ConditionalJumpVariant::Js);
-    }
-    if byte == 0b01110101 {
-        return Some(ConditionalJumpVariant::JneJnz);
-    }
-    if byte == 0b01111101 {
-        return Some(ConditionalJumpVariant::JnlJge);
-    }
-    if byte == 0b01111111 {
-        return Some(ConditionalJumpVariant::JnleJg);
-    }
-    if byte == 0b01110011 {
-        return Some(ConditionalJumpVariant::JnbJae);
-    }
-    if byte == 0b01110111 {
-        return Some(ConditionalJumpVariant::JnbeJa);
-    }
-    if byte == 0b01111011 {
-        return Some(ConditionalJumpVariant::JnpJpo);
-    }
-    if byte == 0b01110001 {
-        return Some(ConditionalJumpVariant::Jno);
-    }
-    if byte == 0b01111001 {
-        return Some(ConditionalJumpVariant::Jns);
-    }
-    if byte == 0b11100010 {
-        return Some(ConditionalJumpVariant::Loop);
-    }
-    if byte == 0b11100001 {
-        return Some(ConditionalJumpVariant::LoopzLoope);
-    }
-    if byte == 0b11100000 {
-        return Some(ConditionalJumpVariant::LoopnzLoopne);
-    }
-    if byte == 0b11100011 {
-        return Some(ConditionalJumpVariant::Jcxz);
-    }
-    None
+enum EncodingMatch {
+    Decoded(DecodedFields),
+    Truncated,
+    Mismatch,
+}
+
+pub fn parse_opcode(
+    program: &[u8],
+    position: usize,
+) -> Result<(Opcode, DecodedFields), DecodeError> {
+    let bytes = &program[position..];
+    let mut truncated = false;
+    for encoding in ENCODINGS {
+        match try_decode_encoding(encoding, bytes) {
+            EncodingMatch::Decoded(fields) => return Ok((encoding.opcode, fields)),
+            EncodingMatch::Truncated => truncated = true,
+            EncodingMatch::Mismatch => {}
+        }
+    }
+    Err(if truncated {
+        DecodeError::Truncated { offset: position }
+    } else {
+        DecodeError::UnknownOpcode {
+            offset: position,
+            byte: bytes[0],
+        }
+    })
+}
+
+fn try_decode_encoding(encoding: &Encoding, bytes: &[u8]) -> EncodingMatch {
+    let mut fields = DecodedFields::default();
+    let mut bit = 0;
+    for field in encoding.fields {
+        let length = field.bit_length();
+        let value = if length > 0 {
+            let Some(byte) = bytes.get(bit / 8) else {
+                return EncodingMatch::Truncated;
+            };
+            let shift = 8 - (bit % 8) - length as usize;
+            bit += length as usize;
+            (((*byte as u16) >> shift) & ((1 << length) - 1)) as u8
+        } else {
+            0
+        };
+        match *field {
+            Field::Bits(_, expected) => {
+                if value != expected {
+                    return EncodingMatch::Mismatch;
+                }
+            }
+            Field::D => fields.d = value,
+            Field::W => fields.w = value,
+            Field::S => fields.s = value,
+            Field::V => fields.v = Some(value),
+            Field::Mod => fields.mode = Some(value),
+            Field::Reg => fields.reg = Some(value),
+            Field::Rm => fields.rm = value,
+            Field::Sr => fields.sr = Some(value),
+            Field::Esc => fields.esc = Some((fields.esc.unwrap_or(0) << 3) | value),
+            Field::Data => fields.data = true,
+            Field::DataIfW => fields.data_if_w = true,
+            Field::RelativeDisp => fields.relative_disp = true,
+            Field::Pointer => fields.pointer = true,
+            Field::PortDx => fields.port_dx = true,
+            Field::ImpD(value) => fields.d = value,
+            Field::ImpW(value) => fields.w = value,
+            Field::ImpReg(value) => fields.reg = Some(value),
+            Field::ImpMod(value) => fields.mode = Some(value),
+            Field::ImpRm(value) => fields.rm = value,
+        }
+    }
+    fields.length = (bit / 8) as u8;
+    EncodingMatch::Decoded(fields)
 }
@@ -1,10 +1,16 @@
-#[derive(Debug)]
+use std::fmt;
+
+use crate::sim8086::dis::{decode_register, RegisterSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MoveVariant {
     RegMemToFromReg,
     ImmToRegMem,
     ImmToReg,
     MemToAcc,
     AccToMem,
+    RegMemToSeg,
+    SegToRegMem,
 }
 
 #[derive(Debug)]
@@ -13,6 +19,7 @@ pub enum Operand {
     EffectiveAddress(EffectiveAddress),
     ImmediateValue(ImmediateValue),
     Register(Register),
+    FarPointer(u16, u16),
 }
 
 #[derive(Debug)]
@@ -23,7 +30,7 @@ pub enum ImmediateValue {
 
 #[derive(Debug)]
 pub enum EffectiveAddress {
-    // JustRegister(Register),
+    JustRegister(Register),
     RegisterAndOffset(Register, Register),
     RegisterAndDisplacement(Register, Displacement),
     RegisterOffsetAndDisplacement(Register, Register, Displacement),
@@ -35,7 +42,7 @@ pub enum Displacement {
     SixteenBits(i16),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Register {
     AX,
     CX,
@@ -53,269 +60,161 @@ pub enum Register {
     CH,
     DH,
     BH,
+    ES,
+    CS,
+    SS,
+    DS,
 }
 
-#[derive(Debug)]
-pub struct GetOperandsParams {
-    pub mode: u8,
-    pub rm: u8,
-    pub w: u8,
+/// Number of displacement bytes that follow a mod/reg/rm byte.
+pub fn displacement_length(mode: u8, rm: u8) -> u8 {
+    match (mode, rm) {
+        (0b00, 0b110) | (0b10, _) => 2,
+        (0b01, _) => 1,
+        _ => 0,
+    }
 }
 
-pub fn get_operand(
-    mode: u8,
-    rm: u8,
-    w: u8,
-    first_displacement_byte: Option<u8>,
-    second_displacement_byte: Option<u8>,
-) -> (Operand, u8) {
-    match rm {
-        0b000 => match mode {
-            0b00 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndOffset(
-                    Register::BX,
-                    Register::SI,
-                )),
-                2,
-            ),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BX,
-                    Register::SI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BX,
-                    Register::SI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::AL), 2),
-                0b1 => (Operand::Register(Register::AX), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b001 => match mode {
-            0b00 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndOffset(
-                    Register::BX,
-                    Register::DI,
-                )),
-                2,
-            ),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BX,
-                    Register::DI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BX,
-                    Register::DI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::CL), 2),
-                0b1 => (Operand::Register(Register::CX), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b010 => match mode {
-            0b00 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndOffset(
-                    Register::BP,
-                    Register::SI,
-                )),
-                2,
-            ),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BP,
-                    Register::SI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BP,
-                    Register::SI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::DL), 2),
-                0b1 => (Operand::Register(Register::DX), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b011 => match mode {
-            0b00 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndOffset(
-                    Register::BP,
-                    Register::DI,
-                )),
-                2,
-            ),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BP,
-                    Register::DI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterOffsetAndDisplacement(
-                    Register::BP,
-                    Register::DI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::BL), 2),
-                0b1 => (Operand::Register(Register::BX), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b100 => match mode {
-            0b00 => (Operand::Register(Register::SI), 2),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::SI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::SI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::AH), 2),
-                0b1 => (Operand::Register(Register::SP), 2),
-                _ => panic!(),
+pub fn get_operand(mode: u8, rm: u8, w: u8, displacement: u16) -> Operand {
+    if mode == 0b11 {
+        return Operand::Register(decode_register(
+            rm,
+            2,
+            if w == 0b1 {
+                RegisterSize::SixteenBits
+            } else {
+                RegisterSize::EightBits
             },
-            _ => panic!(),
-        },
-        0b101 => match mode {
-            0b00 => (Operand::Register(Register::DI), 2),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::DI,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::DI,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::CH), 2),
-                0b1 => (Operand::Register(Register::BP), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b110 => match mode {
-            0b00 => (
-                Operand::Address(
-                    (second_displacement_byte.unwrap() as u16) << 8
-                        | (first_displacement_byte.unwrap() as u16),
-                ),
-                4,
-            ),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::BP,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::BP,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::DH), 2),
-                0b1 => (Operand::Register(Register::SI), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
-        0b111 => match mode {
-            0b00 => (Operand::Register(Register::BX), 2),
-            0b01 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::BX,
-                    Displacement::EightBits(first_displacement_byte.unwrap() as i8),
-                )),
-                3,
-            ),
-            0b10 => (
-                Operand::EffectiveAddress(EffectiveAddress::RegisterAndDisplacement(
-                    Register::BX,
-                    Displacement::SixteenBits(
-                        ((second_displacement_byte.unwrap() as i16) << 8)
-                            | first_displacement_byte.unwrap() as i16,
-                    ),
-                )),
-                4,
-            ),
-            0b11 => match w {
-                0b0 => (Operand::Register(Register::BH), 2),
-                0b1 => (Operand::Register(Register::DI), 2),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        },
+        ));
+    }
+    if mode == 0b00 && rm == 0b110 {
+        return Operand::Address(displacement);
+    }
+    let (base, offset) = match rm {
+        0b000 => (Register::BX, Some(Register::SI)),
+        0b001 => (Register::BX, Some(Register::DI)),
+        0b010 => (Register::BP, Some(Register::SI)),
+        0b011 => (Register::BP, Some(Register::DI)),
+        0b100 => (Register::SI, None),
+        0b101 => (Register::DI, None),
+        0b110 => (Register::BP, None),
+        0b111 => (Register::BX, None),
         _ => panic!(),
+    };
+    let displacement = match mode {
+        0b00 => None,
+        0b01 => Some(Displacement::EightBits(displacement as i8)),
+        _ => Some(Displacement::SixteenBits(displacement as i16)),
+    };
+    Operand::EffectiveAddress(match (offset, displacement) {
+        (None, None) => EffectiveAddress::JustRegister(base),
+        (Some(offset), None) => EffectiveAddress::RegisterAndOffset(base, offset),
+        (None, Some(disp)) => EffectiveAddress::RegisterAndDisplacement(base, disp),
+        (Some(offset), Some(disp)) => {
+            EffectiveAddress::RegisterOffsetAndDisplacement(base, offset, disp)
+        }
+    })
+}
+
+impl fmt::Display for MoveVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("mov")
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Address(address) => write!(f, "[{}]", address),
+            Operand::EffectiveAddress(address) => write!(f, "{}", address),
+            Operand::ImmediateValue(value) => write!(f, "{}", value),
+            Operand::Register(register) => write!(f, "{}", register),
+            Operand::FarPointer(segment, offset) => write!(f, "{}:{}", segment, offset),
+        }
+    }
+}
+
+impl fmt::Display for ImmediateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImmediateValue::EightBits(value) => write!(f, "{}", value),
+            ImmediateValue::SixteenBits(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl ImmediateValue {
+    pub fn signed(&self) -> i16 {
+        match self {
+            ImmediateValue::EightBits(value) => *value as i16,
+            ImmediateValue::SixteenBits(value) => *value,
+        }
+    }
+
+    /// The value read as unsigned, as ports, interrupt numbers and pop counts are.
+    pub fn unsigned(&self) -> u16 {
+        match self {
+            ImmediateValue::EightBits(value) => *value as u8 as u16,
+            ImmediateValue::SixteenBits(value) => *value as u16,
+        }
+    }
+}
+
+impl fmt::Display for EffectiveAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EffectiveAddress::JustRegister(base) => write!(f, "[{}]", base),
+            EffectiveAddress::RegisterAndOffset(base, offset) => {
+                write!(f, "[{} + {}]", base, offset)
+            }
+            EffectiveAddress::RegisterAndDisplacement(base, disp) => {
+                write!(f, "[{}{}]", base, disp)
+            }
+            EffectiveAddress::RegisterOffsetAndDisplacement(base, offset, disp) => {
+                write!(f, "[{} + {}{}]", base, offset, disp)
+            }
+        }
+    }
+}
+
+/// Written as the ` + n` / ` - n` tail of an effective address, nothing when zero.
+impl fmt::Display for Displacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Displacement::EightBits(value) => *value as i32,
+            Displacement::SixteenBits(value) => *value as i32,
+        };
+        match value {
+            0 => Ok(()),
+            1.. => write!(f, " + {}", value),
+            _ => write!(f, " - {}", -value),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Register::AX => "ax",
+            Register::CX => "cx",
+            Register::DX => "dx",
+            Register::BX => "bx",
+            Register::SP => "sp",
+            Register::BP => "bp",
+            Register::SI => "si",
+            Register::DI => "di",
+            Register::AL => "al",
+            Register::CL => "cl",
+            Register::DL => "dl",
+            Register::BL => "bl",
+            Register::AH => "ah",
+            Register::CH => "ch",
+            Register::DH => "dh",
+            Register::BH => "bh",
+            Register::ES => "es",
+            Register::CS => "cs",
+            Register::SS => "ss",
+            Register::DS => "ds",
+        })
     }
 }
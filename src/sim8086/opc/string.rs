@@ -0,0 +1,23 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringFamily {
+    Movs,
+    Cmps,
+    Scas,
+    Lods,
+    Stos,
+}
+
+/// Only the stem, the instruction appends the `b`/`w` size suffix.
+impl fmt::Display for StringFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StringFamily::Movs => "movs",
+            StringFamily::Cmps => "cmps",
+            StringFamily::Scas => "scas",
+            StringFamily::Lods => "lods",
+            StringFamily::Stos => "stos",
+        })
+    }
+}
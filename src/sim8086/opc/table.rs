@@ -0,0 +1,256 @@
+use super::{
+    arith::{AdjustFamily, ArithmeticFamily, ArithmeticVariant, UnaryFamily},
+    cond_jump::ConditionalJumpVariant,
+    control::{PrefixVariant, ProcessorFamily},
+    jump::{InterruptVariant, JumpVariant, ReturnVariant},
+    logic::ShiftFamily,
+    mov::{MoveVariant, Register},
+    string::StringFamily,
+    transfer::TransferFamily,
+    Opcode,
+};
+
+/// One piece of an instruction encoding, in the order it appears in the byte stream.
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    /// Literal `(length, value)` bits that have to match for the encoding to apply.
+    Bits(u8, u8),
+    D,
+    W,
+    S,
+    V,
+    Mod,
+    Reg,
+    Rm,
+    Sr,
+    /// Three bits of the six bit ESC opcode, split around the mod field.
+    Esc,
+    /// Immediate byte, or word when followed by `DataIfW` and w = 1 and s = 0.
+    Data,
+    DataIfW,
+    /// IP relative displacement of a jump, a word when w = 1.
+    RelativeDisp,
+    /// Offset and segment words of a direct intersegment transfer.
+    Pointer,
+    /// DX used as the port of a variable port IN/OUT.
+    PortDx,
+    ImpD(u8),
+    ImpW(u8),
+    ImpReg(u8),
+    ImpMod(u8),
+    ImpRm(u8),
+}
+
+impl Field {
+    pub fn bit_length(&self) -> u8 {
+        match self {
+            Field::Bits(length, _) => *length,
+            Field::D | Field::W | Field::S | Field::V => 1,
+            Field::Mod | Field::Sr => 2,
+            Field::Reg | Field::Rm | Field::Esc => 3,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Encoding {
+    pub opcode: Opcode,
+    pub fields: &'static [Field],
+}
+
+use Field::*;
+
+const fn enc(opcode: Opcode, fields: &'static [Field]) -> Encoding {
+    Encoding { opcode, fields }
+}
+
+const fn mov(variant: MoveVariant) -> Opcode {
+    Opcode::Move { variant }
+}
+
+const fn arith(family: ArithmeticFamily, variant: ArithmeticVariant) -> Opcode {
+    Opcode::Arithmetic { variant, family }
+}
+
+const fn unary(family: UnaryFamily) -> Opcode {
+    Opcode::Unary { family }
+}
+
+const fn adjust(family: AdjustFamily) -> Opcode {
+    Opcode::Adjust { family }
+}
+
+const fn shift(family: ShiftFamily) -> Opcode {
+    Opcode::Shift { family }
+}
+
+const fn string(family: StringFamily) -> Opcode {
+    Opcode::String { family }
+}
+
+const fn transfer(family: TransferFamily) -> Opcode {
+    Opcode::Transfer { family }
+}
+
+const fn cond_jump(variant: ConditionalJumpVariant) -> Opcode {
+    Opcode::ConditionalJump { variant }
+}
+
+const fn processor(family: ProcessorFamily) -> Opcode {
+    Opcode::Processor { family }
+}
+
+const fn prefix(variant: PrefixVariant) -> Opcode {
+    Opcode::Prefix { variant }
+}
+
+/// Every documented 8086 encoding, following the layout of the Intel 8086 manual (table 4-12).
+/// Entries are tried in order, the first one whose literal bits match wins.
+#[rustfmt::skip]
+pub const ENCODINGS: &[Encoding] = &[
+    // Data transfer
+    enc(mov(MoveVariant::RegMemToFromReg), &[Bits(6, 0b100010), D, W, Mod, Reg, Rm]),
+    enc(mov(MoveVariant::ImmToRegMem), &[Bits(7, 0b1100011), W, Mod, Bits(3, 0b000), Rm, Data, DataIfW]),
+    enc(mov(MoveVariant::ImmToReg), &[Bits(4, 0b1011), W, Reg, Data, DataIfW, ImpD(1)]),
+    enc(mov(MoveVariant::MemToAcc), &[Bits(7, 0b1010000), W, ImpReg(0), ImpMod(0b00), ImpRm(0b110), ImpD(1)]),
+    enc(mov(MoveVariant::AccToMem), &[Bits(7, 0b1010001), W, ImpReg(0), ImpMod(0b00), ImpRm(0b110), ImpD(0)]),
+    enc(mov(MoveVariant::RegMemToSeg), &[Bits(8, 0b10001110), Mod, Bits(1, 0b0), Sr, Rm, ImpD(1), ImpW(1)]),
+    enc(mov(MoveVariant::SegToRegMem), &[Bits(8, 0b10001100), Mod, Bits(1, 0b0), Sr, Rm, ImpW(1)]),
+    enc(Opcode::Push, &[Bits(8, 0b11111111), Mod, Bits(3, 0b110), Rm, ImpW(1)]),
+    enc(Opcode::Push, &[Bits(5, 0b01010), Reg, ImpW(1)]),
+    enc(Opcode::Push, &[Bits(3, 0b000), Sr, Bits(3, 0b110), ImpW(1)]),
+    enc(Opcode::Pop, &[Bits(8, 0b10001111), Mod, Bits(3, 0b000), Rm, ImpW(1)]),
+    enc(Opcode::Pop, &[Bits(5, 0b01011), Reg, ImpW(1)]),
+    enc(Opcode::Pop, &[Bits(3, 0b000), Sr, Bits(3, 0b111), ImpW(1)]),
+    enc(Opcode::Exchange, &[Bits(7, 0b1000011), W, Mod, Reg, Rm, ImpD(1)]),
+    enc(Opcode::Exchange, &[Bits(5, 0b10010), Reg, ImpMod(0b11), ImpRm(0b000), ImpW(1)]),
+    enc(Opcode::Input, &[Bits(7, 0b1110010), W, Data, ImpReg(0), ImpD(1)]),
+    enc(Opcode::Input, &[Bits(7, 0b1110110), W, PortDx, ImpReg(0), ImpD(1)]),
+    enc(Opcode::Output, &[Bits(7, 0b1110011), W, Data, ImpReg(0)]),
+    enc(Opcode::Output, &[Bits(7, 0b1110111), W, PortDx, ImpReg(0)]),
+    enc(transfer(TransferFamily::Xlat), &[Bits(8, 0b11010111)]),
+    enc(transfer(TransferFamily::Lea), &[Bits(8, 0b10001101), Mod, Reg, Rm, ImpD(1), ImpW(1)]),
+    enc(transfer(TransferFamily::Lds), &[Bits(8, 0b11000101), Mod, Reg, Rm, ImpD(1), ImpW(1)]),
+    enc(transfer(TransferFamily::Les), &[Bits(8, 0b11000100), Mod, Reg, Rm, ImpD(1), ImpW(1)]),
+    enc(transfer(TransferFamily::Lahf), &[Bits(8, 0b10011111)]),
+    enc(transfer(TransferFamily::Sahf), &[Bits(8, 0b10011110)]),
+    enc(transfer(TransferFamily::Pushf), &[Bits(8, 0b10011100)]),
+    enc(transfer(TransferFamily::Popf), &[Bits(8, 0b10011101)]),
+    // Arithmetic
+    enc(arith(ArithmeticFamily::Add, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b000000), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Add, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b000), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Add, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0000010), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(arith(ArithmeticFamily::Adc, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b000100), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Adc, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b010), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Adc, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0001010), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(unary(UnaryFamily::Inc), &[Bits(7, 0b1111111), W, Mod, Bits(3, 0b000), Rm]),
+    enc(unary(UnaryFamily::Inc), &[Bits(5, 0b01000), Reg, ImpW(1)]),
+    enc(adjust(AdjustFamily::Aaa), &[Bits(8, 0b00110111)]),
+    enc(adjust(AdjustFamily::Daa), &[Bits(8, 0b00100111)]),
+    enc(arith(ArithmeticFamily::Sub, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b001010), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Sub, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b101), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Sub, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0010110), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(arith(ArithmeticFamily::Sbb, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b000110), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Sbb, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b011), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Sbb, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0001110), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(unary(UnaryFamily::Dec), &[Bits(7, 0b1111111), W, Mod, Bits(3, 0b001), Rm]),
+    enc(unary(UnaryFamily::Dec), &[Bits(5, 0b01001), Reg, ImpW(1)]),
+    enc(unary(UnaryFamily::Neg), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b011), Rm]),
+    enc(arith(ArithmeticFamily::Cmp, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b001110), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Cmp, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b111), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Cmp, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0011110), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(adjust(AdjustFamily::Aas), &[Bits(8, 0b00111111)]),
+    enc(adjust(AdjustFamily::Das), &[Bits(8, 0b00101111)]),
+    enc(unary(UnaryFamily::Mul), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b100), Rm]),
+    enc(unary(UnaryFamily::Imul), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b101), Rm]),
+    enc(adjust(AdjustFamily::Aam), &[Bits(8, 0b11010100), Bits(8, 0b00001010)]),
+    enc(unary(UnaryFamily::Div), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b110), Rm]),
+    enc(unary(UnaryFamily::Idiv), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b111), Rm]),
+    enc(adjust(AdjustFamily::Aad), &[Bits(8, 0b11010101), Bits(8, 0b00001010)]),
+    enc(transfer(TransferFamily::Cbw), &[Bits(8, 0b10011000)]),
+    enc(transfer(TransferFamily::Cwd), &[Bits(8, 0b10011001)]),
+    // Logic
+    enc(unary(UnaryFamily::Not), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b010), Rm]),
+    enc(shift(ShiftFamily::ShlSal), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b100), Rm]),
+    enc(shift(ShiftFamily::Shr), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b101), Rm]),
+    enc(shift(ShiftFamily::Sar), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b111), Rm]),
+    enc(shift(ShiftFamily::Rol), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b000), Rm]),
+    enc(shift(ShiftFamily::Ror), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b001), Rm]),
+    enc(shift(ShiftFamily::Rcl), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b010), Rm]),
+    enc(shift(ShiftFamily::Rcr), &[Bits(6, 0b110100), V, W, Mod, Bits(3, 0b011), Rm]),
+    enc(arith(ArithmeticFamily::And, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b001000), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::And, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b100), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::And, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0010010), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(arith(ArithmeticFamily::Test, ArithmeticVariant::RegMemAndRegEither), &[Bits(7, 0b1000010), W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Test, ArithmeticVariant::ImmRegMem), &[Bits(7, 0b1111011), W, Mod, Bits(3, 0b000), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Test, ArithmeticVariant::ImmAcc), &[Bits(7, 0b1010100), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(arith(ArithmeticFamily::Or, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b000010), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Or, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b001), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Or, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0000110), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    enc(arith(ArithmeticFamily::Xor, ArithmeticVariant::RegMemAndRegEither), &[Bits(6, 0b001100), D, W, Mod, Reg, Rm]),
+    enc(arith(ArithmeticFamily::Xor, ArithmeticVariant::ImmRegMem), &[Bits(6, 0b100000), S, W, Mod, Bits(3, 0b110), Rm, Data, DataIfW]),
+    enc(arith(ArithmeticFamily::Xor, ArithmeticVariant::ImmAcc), &[Bits(7, 0b0011010), W, Data, DataIfW, ImpReg(0), ImpD(1)]),
+    // String manipulation
+    enc(prefix(PrefixVariant::Repne), &[Bits(8, 0b11110010)]),
+    enc(prefix(PrefixVariant::Rep), &[Bits(8, 0b11110011)]),
+    enc(string(StringFamily::Movs), &[Bits(7, 0b1010010), W]),
+    enc(string(StringFamily::Cmps), &[Bits(7, 0b1010011), W]),
+    enc(string(StringFamily::Scas), &[Bits(7, 0b1010111), W]),
+    enc(string(StringFamily::Lods), &[Bits(7, 0b1010110), W]),
+    enc(string(StringFamily::Stos), &[Bits(7, 0b1010101), W]),
+    // Control transfer
+    enc(Opcode::Call { variant: JumpVariant::Near }, &[Bits(8, 0b11101000), RelativeDisp, ImpW(1)]),
+    enc(Opcode::Call { variant: JumpVariant::NearIndirect }, &[Bits(8, 0b11111111), Mod, Bits(3, 0b010), Rm, ImpW(1)]),
+    enc(Opcode::Call { variant: JumpVariant::Far }, &[Bits(8, 0b10011010), Pointer]),
+    enc(Opcode::Call { variant: JumpVariant::FarIndirect }, &[Bits(8, 0b11111111), Mod, Bits(3, 0b011), Rm, ImpW(1)]),
+    enc(Opcode::Jump { variant: JumpVariant::Near }, &[Bits(8, 0b11101001), RelativeDisp, ImpW(1)]),
+    enc(Opcode::Jump { variant: JumpVariant::Short }, &[Bits(8, 0b11101011), RelativeDisp]),
+    enc(Opcode::Jump { variant: JumpVariant::NearIndirect }, &[Bits(8, 0b11111111), Mod, Bits(3, 0b100), Rm, ImpW(1)]),
+    enc(Opcode::Jump { variant: JumpVariant::Far }, &[Bits(8, 0b11101010), Pointer]),
+    enc(Opcode::Jump { variant: JumpVariant::FarIndirect }, &[Bits(8, 0b11111111), Mod, Bits(3, 0b101), Rm, ImpW(1)]),
+    enc(Opcode::Return { variant: ReturnVariant::Near }, &[Bits(8, 0b11000011)]),
+    enc(Opcode::Return { variant: ReturnVariant::Near }, &[Bits(8, 0b11000010), Data, DataIfW, ImpW(1)]),
+    enc(Opcode::Return { variant: ReturnVariant::Far }, &[Bits(8, 0b11001011)]),
+    enc(Opcode::Return { variant: ReturnVariant::Far }, &[Bits(8, 0b11001010), Data, DataIfW, ImpW(1)]),
+    enc(cond_jump(ConditionalJumpVariant::JeJz), &[Bits(8, 0b01110100), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JlJnge), &[Bits(8, 0b01111100), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JleJng), &[Bits(8, 0b01111110), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JbJnae), &[Bits(8, 0b01110010), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JbeJna), &[Bits(8, 0b01110110), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JpJpe), &[Bits(8, 0b01111010), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Jo), &[Bits(8, 0b01110000), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Js), &[Bits(8, 0b01111000), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JneJnz), &[Bits(8, 0b01110101), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JnlJge), &[Bits(8, 0b01111101), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JnleJg), &[Bits(8, 0b01111111), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JnbJae), &[Bits(8, 0b01110011), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JnbeJa), &[Bits(8, 0b01110111), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::JnpJpo), &[Bits(8, 0b01111011), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Jno), &[Bits(8, 0b01110001), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Jns), &[Bits(8, 0b01111001), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Loop), &[Bits(8, 0b11100010), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::LoopzLoope), &[Bits(8, 0b11100001), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::LoopnzLoopne), &[Bits(8, 0b11100000), RelativeDisp]),
+    enc(cond_jump(ConditionalJumpVariant::Jcxz), &[Bits(8, 0b11100011), RelativeDisp]),
+    enc(Opcode::Interrupt { variant: InterruptVariant::Int }, &[Bits(8, 0b11001101), Data]),
+    enc(Opcode::Interrupt { variant: InterruptVariant::Int3 }, &[Bits(8, 0b11001100)]),
+    enc(Opcode::Interrupt { variant: InterruptVariant::Into }, &[Bits(8, 0b11001110)]),
+    enc(Opcode::Interrupt { variant: InterruptVariant::Iret }, &[Bits(8, 0b11001111)]),
+    // Processor control
+    enc(processor(ProcessorFamily::Clc), &[Bits(8, 0b11111000)]),
+    enc(processor(ProcessorFamily::Cmc), &[Bits(8, 0b11110101)]),
+    enc(processor(ProcessorFamily::Stc), &[Bits(8, 0b11111001)]),
+    enc(processor(ProcessorFamily::Cld), &[Bits(8, 0b11111100)]),
+    enc(processor(ProcessorFamily::Std), &[Bits(8, 0b11111101)]),
+    enc(processor(ProcessorFamily::Cli), &[Bits(8, 0b11111010)]),
+    enc(processor(ProcessorFamily::Sti), &[Bits(8, 0b11111011)]),
+    enc(processor(ProcessorFamily::Hlt), &[Bits(8, 0b11110100)]),
+    enc(processor(ProcessorFamily::Wait), &[Bits(8, 0b10011011)]),
+    enc(Opcode::Escape, &[Bits(5, 0b11011), Esc, Mod, Esc, Rm]),
+    enc(prefix(PrefixVariant::Lock), &[Bits(8, 0b11110000)]),
+    enc(prefix(PrefixVariant::Segment(Register::ES)), &[Bits(8, 0b00100110)]),
+    enc(prefix(PrefixVariant::Segment(Register::CS)), &[Bits(8, 0b00101110)]),
+    enc(prefix(PrefixVariant::Segment(Register::SS)), &[Bits(8, 0b00110110)]),
+    enc(prefix(PrefixVariant::Segment(Register::DS)), &[Bits(8, 0b00111110)]),
+];
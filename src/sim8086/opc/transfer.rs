@@ -0,0 +1,32 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFamily {
+    Xlat,
+    Lea,
+    Lds,
+    Les,
+    Lahf,
+    Sahf,
+    Pushf,
+    Popf,
+    Cbw,
+    Cwd,
+}
+
+impl fmt::Display for TransferFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransferFamily::Xlat => "xlatb",
+            TransferFamily::Lea => "lea",
+            TransferFamily::Lds => "lds",
+            TransferFamily::Les => "les",
+            TransferFamily::Lahf => "lahf",
+            TransferFamily::Sahf => "sahf",
+            TransferFamily::Pushf => "pushf",
+            TransferFamily::Popf => "popf",
+            TransferFamily::Cbw => "cbw",
+            TransferFamily::Cwd => "cwd",
+        })
+    }
+}
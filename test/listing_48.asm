@@ -0,0 +1,6 @@
+bits 16
+mov cx, 200
+mov bx, cx
+add cx, 1000
+mov bx, 2000
+sub cx, bx
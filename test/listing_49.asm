@@ -0,0 +1,7 @@
+bits 16
+mov cx, 3
+mov bx, 1000
+label_0:
+add bx, 10
+sub cx, 1
+jne label_0
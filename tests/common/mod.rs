@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests.
+
+// Each test file uses its own share of these.
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use computer_enhance_8086::sim8086::{
+    dis::{Dissassembler, Instruction},
+    emu::{Emulator, EmulatorError},
+};
+
+/// Runs `program` from IP 0, decoded from the buffer rather than memory, until it runs off its
+/// end or terminates.
+pub fn run_on(emulator: Emulator, program: &[u8]) -> Result<Emulator, EmulatorError> {
+    let dissassembler = Dissassembler::new(program);
+    run_with(emulator, |emulator| {
+        let at = emulator.instruction_pointer as usize;
+        (at < program.len()).then(|| dissassembler.get_instruction_at(at).unwrap())
+    })
+}
+
+/// Runs the program loaded in memory from CS:IP until it terminates.
+pub fn run_loaded(emulator: Emulator) -> Result<Emulator, EmulatorError> {
+    run_with(emulator, |emulator| {
+        Some(emulator.fetch_instruction().unwrap())
+    })
+}
+
+fn run_with(
+    mut emulator: Emulator,
+    fetch: impl Fn(&Emulator) -> Option<Instruction>,
+) -> Result<Emulator, EmulatorError> {
+    while emulator.termination().is_none() {
+        let Some(instruction) = fetch(&emulator) else {
+            break;
+        };
+        emulator.execute_instruction(&instruction)?;
+    }
+    Ok(emulator)
+}
+
+/// Console output the test can still read once the emulator owns the DOS services.
+#[derive(Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    pub fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
@@ -0,0 +1,259 @@
+//! Decoding checks on small hand assembled byte sequences, for what the listings don't cover.
+
+use computer_enhance_8086::sim8086::dis::{DecodeError, Dissassembler};
+
+fn disassemble(program: &[u8]) -> Vec<String> {
+    Dissassembler::new(program)
+        .instructions()
+        .map(|result| match result {
+            Ok((offset, instruction)) => format!("{:04x} {}", offset, instruction),
+            Err(err) => format!("; {}", err),
+        })
+        .collect()
+}
+
+#[test]
+fn prefixes_belong_to_the_following_instruction() {
+    let program = [
+        0x26, 0x8b, 0x07, // mov ax, es:[bx]
+        0xf0, 0x87, 0x07, // lock xchg ax, [bx]
+        0xf3, 0xa4, // rep movsb
+        0xf3, 0xa6, // repe cmpsb
+        0xf2, 0xaf, // repne scasw
+        0x2e, 0xc7, 0x46, 0x02, 0x05, 0x00, // mov word cs:[bp + 2], 5
+        0x2e, 0xa4, // cs movsb
+        0xf3, 0x26, 0xad, // rep es lodsw
+        0x2e, 0xd7, // cs xlatb
+    ];
+    assert_eq!(
+        disassemble(&program),
+        [
+            "0000 mov ax, es:[bx]",
+            "0003 lock xchg ax, [bx]",
+            "0006 rep movsb",
+            "0008 repe cmpsb",
+            "000a repne scasw",
+            "000c mov word cs:[bp + 2], 5",
+            "0012 cs movsb",
+            "0014 rep es lodsw",
+            "0017 cs xlatb",
+        ]
+    );
+}
+
+#[test]
+fn one_encoding_of_each_family() {
+    let program = [
+        0xf6, 0x07, 0x05, // test byte [bx], 5
+        0xa8, 0x0f, // test al, 15
+        0xd0, 0xe0, // shl al, 1
+        0xd1, 0xf8, // sar ax, 1
+        0xd2, 0xc8, // ror al, cl
+        0xd3, 0x2f, // shr word [bx], cl
+        0xa4, // movsb
+        0xa7, // cmpsw
+        0xaa, // stosb
+        0xad, // lodsw
+        0xae, // scasb
+        0xe4, 0x60, // in al, 96
+        0xed, // in ax, dx
+        0xe7, 0x61, // out 97, ax
+        0xee, // out dx, al
+        0x07, // pop es
+        0x1f, // pop ds
+        0x93, // xchg ax, bx
+        0xd4, 0x0a, // aam
+        0xd5, 0x0a, // aad
+        0xea, 0x34, 0x12, 0x00, 0x10, // jmp 4096:4660
+        0x9a, 0x78, 0x56, 0x00, 0x20, // call 8192:22136
+        0xff, 0x2f, // jmp far [bx]
+        0xff, 0x1e, 0x10, 0x00, // call far [16]
+    ];
+    assert_eq!(
+        disassemble(&program),
+        [
+            "0000 test byte [bx], 5",
+            "0003 test al, 15",
+            "0005 shl al, 1",
+            "0007 sar ax, 1",
+            "0009 ror al, cl",
+            "000b shr word [bx], cl",
+            "000d movsb",
+            "000e cmpsw",
+            "000f stosb",
+            "0010 lodsw",
+            "0011 scasb",
+            "0012 in al, 96",
+            "0014 in ax, dx",
+            "0015 out 97, ax",
+            "0017 out dx, al",
+            "0018 pop es",
+            "0019 pop ds",
+            "001a xchg ax, bx",
+            "001b aam",
+            "001d aad",
+            "001f jmp 4096:4660",
+            "0024 call 8192:22136",
+            "0029 jmp far [bx]",
+            "002b call far [16]",
+        ]
+    );
+}
+
+#[test]
+fn port_comes_first_in_out() {
+    let program = [
+        0xe4, 0x60, // in al, 96
+        0xed, // in ax, dx
+        0xe6, 0x61, // out 97, al
+        0xef, // out dx, ax
+    ];
+    assert_eq!(
+        disassemble(&program),
+        [
+            "0000 in al, 96",
+            "0002 in ax, dx",
+            "0003 out 97, al",
+            "0005 out dx, ax"
+        ]
+    );
+}
+
+#[test]
+fn esc_is_listed_as_its_bytes() {
+    let program = [
+        0xd8, 0x07, // esc 0, [bx]
+        0xdf, 0x26, 0x7a, 0x02, // esc 60, [634]
+        0xf0, 0x26, 0xdf, 0x7a, 0x02, // lock esc 63, es:[bp + si + 2]
+        0xde, 0xc1, // esc 48, cl
+    ];
+    let (listing, error) = Dissassembler::new(&program).listing(false);
+    assert_eq!(error, None);
+    assert_eq!(
+        listing,
+        "bits 16\n\
+         db 0xd8, 0x07\n\
+         db 0xdf, 0x26, 0x7a, 0x02\n\
+         db 0xf0, 0x26, 0xdf, 0x7a, 0x02\n\
+         db 0xde, 0xc1\n"
+    );
+    assert_eq!(
+        disassemble(&program),
+        [
+            "0000 esc 0, [bx]",
+            "0002 esc 60, [634]",
+            "0006 lock esc 63, es:[bp + si + 2]",
+            "000b esc 48, cl",
+        ]
+    );
+}
+
+#[test]
+fn decode_errors_report_where_decoding_failed() {
+    let error_at = |program: &[u8], position: usize| {
+        Dissassembler::new(program)
+            .get_instruction_at(position)
+            .unwrap_err()
+    };
+    // salc is not an 8086 instruction
+    let error = error_at(&[0x90, 0xd6], 1);
+    assert_eq!(
+        error,
+        DecodeError::UnknownOpcode {
+            offset: 1,
+            byte: 0xd6
+        }
+    );
+    assert_eq!(error.offset(), 1);
+    // FE /7 is not used
+    assert_eq!(
+        error_at(&[0xfe, 0xf8], 0),
+        DecodeError::UnknownOpcode {
+            offset: 0,
+            byte: 0xfe
+        }
+    );
+    // The unknown byte is after the prefix.
+    assert_eq!(
+        error_at(&[0x90, 0x2e, 0xd6], 1),
+        DecodeError::UnknownOpcode {
+            offset: 2,
+            byte: 0xd6
+        }
+    );
+    // lea ax, ax
+    assert_eq!(
+        error_at(&[0x90, 0x8d, 0xc0], 1),
+        DecodeError::UnsupportedModRm {
+            offset: 1,
+            mode: 0b11,
+            rm: 0b000
+        }
+    );
+    // The instruction still starts at the prefix.
+    let error = error_at(&[0x26, 0x8d, 0xc0], 0);
+    assert_eq!(
+        error,
+        DecodeError::UnsupportedModRm {
+            offset: 0,
+            mode: 0b11,
+            rm: 0b000
+        }
+    );
+    assert_eq!(error.offset(), 0);
+}
+
+#[test]
+fn listing_stops_at_the_first_decode_error() {
+    assert_eq!(
+        disassemble(&[0x90, 0x8d, 0xc0, 0x90]),
+        [
+            "0000 xchg ax, ax",
+            "; unsupported mod 11 with r/m 000 at offset 0x0001"
+        ]
+    );
+}
+
+#[test]
+fn prefix_without_an_instruction_is_truncated() {
+    let program = [0x90, 0xf3, 0x26];
+    let dissassembler = Dissassembler::new(&program);
+    assert_eq!(
+        dissassembler.get_instruction_at(1).unwrap_err(),
+        DecodeError::Truncated { offset: 1 }
+    );
+}
+
+#[test]
+fn jump_targets_become_labels_or_absolute_offsets() {
+    let program = [
+        0xeb, 0x03, // jmp short label_0
+        0xe9, 0x00, 0x01, // jmp near 0x0105
+        0xe8, 0xfd, 0xff, // label_0: call label_0
+        0xea, 0x00, 0x01, 0x00, 0x20, // jmp 8192:256
+        0xff, 0xe3, // jmp bx
+        0xff, 0x2f, // jmp far [bx]
+    ];
+    let dissassembler = Dissassembler::new(&program);
+    let labels = dissassembler.labels();
+    let listing: Vec<String> = dissassembler
+        .instructions()
+        .map(|result| {
+            let (offset, instruction) = result.unwrap();
+            let line = instruction.display_at(offset, &labels).to_string();
+            line
+        })
+        .collect();
+    assert_eq!(
+        listing,
+        [
+            "jmp short label_0",
+            "jmp near 261",
+            "call label_0",
+            "jmp 8192:256",
+            "jmp bx",
+            "jmp far [bx]",
+        ]
+    );
+    assert_eq!(labels.get(&5).map(String::as_str), Some("label_0"));
+}
@@ -0,0 +1,159 @@
+//! The host DOS services, driven by small hand assembled programs.
+
+mod common;
+
+use std::{env, fs, io, path::PathBuf};
+
+use computer_enhance_8086::sim8086::{
+    dos::Dos,
+    emu::{Emulator, Termination},
+    opc::mov::Register,
+};
+
+use common::{run_on, Output};
+
+/// An empty directory of its own for each test.
+fn directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("dos-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Runs `program` with `data` at 0x200 until it ends or terminates.
+fn run(dos: Dos, data: &[u8], program: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new();
+    emulator.set_interrupt_hook(dos);
+    for (i, byte) in data.iter().enumerate() {
+        emulator.memory_mut().write_u8(0, 0x200 + i as u16, *byte);
+    }
+    run_on(emulator, program).unwrap()
+}
+
+#[test]
+fn prints_and_terminates_with_the_exit_code() {
+    let output = Output::default();
+    let dos = Dos::with_io(directory("print"), io::empty(), output.clone(), io::sink());
+    // mov dx, 0x200 / mov ah, 9 / int 0x21 / mov dl, '!' / mov ah, 2 / int 0x21
+    // mov ax, 0x4c03 / int 0x21 / mov cx, 1
+    let emulator = run(
+        dos,
+        b"Hello, world$",
+        &[
+            0xba, 0x00, 0x02, 0xb4, 0x09, 0xcd, 0x21, 0xb2, 0x21, 0xb4, 0x02, 0xcd, 0x21, 0xb8,
+            0x03, 0x4c, 0xcd, 0x21, 0xb9, 0x01, 0x00,
+        ],
+    );
+    assert_eq!(output.text(), "Hello, world!");
+    assert_eq!(
+        emulator.termination(),
+        Some(Termination::Exited { code: 3 })
+    );
+    assert_eq!(emulator.register_value(Register::CX), 0);
+}
+
+#[test]
+fn reads_characters_and_buffered_lines() {
+    let output = Output::default();
+    let dos = Dos::with_io(
+        directory("keyboard"),
+        &b"abline two\n"[..],
+        output.clone(),
+        io::sink(),
+    );
+    // mov ah, 1 / int 0x21 / mov bl, al / mov ah, 8 / int 0x21 / mov bh, al
+    // mov dx, 0x200 / mov ah, 0x0a / int 0x21
+    let emulator = run(
+        dos,
+        &[5],
+        &[
+            0xb4, 0x01, 0xcd, 0x21, 0x88, 0xc3, 0xb4, 0x08, 0xcd, 0x21, 0x88, 0xc7, 0xba, 0x00,
+            0x02, 0xb4, 0x0a, 0xcd, 0x21,
+        ],
+    );
+    assert_eq!(output.text(), "a");
+    assert_eq!(emulator.register_value(Register::BX), 0x6261);
+    let line: Vec<u8> = (0x201..0x207)
+        .map(|offset| emulator.memory().read_u8(0, offset))
+        .collect();
+    assert_eq!(line, b"\x04line\r");
+}
+
+#[test]
+fn handle_2_writes_to_the_error_output() {
+    let output = Output::default();
+    let error = Output::default();
+    let dos = Dos::with_io(
+        directory("stderr"),
+        io::empty(),
+        output.clone(),
+        error.clone(),
+    );
+    // mov bx, 2 / mov cx, 4 / mov dx, 0x200 / mov ah, 0x40 / int 0x21
+    // mov bx, 1 / mov cx, 2 / mov ah, 0x40 / int 0x21
+    let emulator = run(
+        dos,
+        b"oops",
+        &[
+            0xbb, 0x02, 0x00, 0xb9, 0x04, 0x00, 0xba, 0x00, 0x02, 0xb4, 0x40, 0xcd, 0x21, 0xbb,
+            0x01, 0x00, 0xb9, 0x02, 0x00, 0xb4, 0x40, 0xcd, 0x21,
+        ],
+    );
+    assert_eq!(error.text(), "oops");
+    assert_eq!(output.text(), "oo");
+    assert_eq!(emulator.register_value(Register::AX), 2);
+    assert!(!emulator.flags().carry());
+}
+
+#[test]
+fn opens_seeks_reads_and_closes_files() {
+    let directory = directory("files");
+    fs::write(directory.join("in.txt"), "abcdef").unwrap();
+    let output = Output::default();
+    let dos = Dos::with_io(&directory, io::empty(), output.clone(), io::sink());
+    // mov dx, 0x200 / mov ax, 0x3d00 / int 0x21 / mov bx, ax
+    // mov ax, 0x4200 / mov cx, 0 / mov dx, 2 / int 0x21
+    // mov ah, 0x3f / mov cx, 3 / mov dx, 0x300 / int 0x21
+    // mov si, bx / mov bx, 1 / mov ah, 0x40 / mov cx, 3 / mov dx, 0x300 / int 0x21
+    // mov bx, si / mov ah, 0x3e / int 0x21 / mov ah, 0x3e / int 0x21
+    let emulator = run(
+        dos,
+        b"in.txt\0",
+        &[
+            0xba, 0x00, 0x02, 0xb8, 0x00, 0x3d, 0xcd, 0x21, 0x89, 0xc3, 0xb8, 0x00, 0x42, 0xb9,
+            0x00, 0x00, 0xba, 0x02, 0x00, 0xcd, 0x21, 0xb4, 0x3f, 0xb9, 0x03, 0x00, 0xba, 0x00,
+            0x03, 0xcd, 0x21, 0x89, 0xde, 0xbb, 0x01, 0x00, 0xb4, 0x40, 0xb9, 0x03, 0x00, 0xba,
+            0x00, 0x03, 0xcd, 0x21, 0x89, 0xf3, 0xb4, 0x3e, 0xcd, 0x21, 0xb4, 0x3e, 0xcd, 0x21,
+        ],
+    );
+    assert_eq!(output.text(), "cde");
+    assert_eq!(emulator.register_value(Register::SI), 5);
+    // The second close has nothing left to close.
+    assert!(emulator.flags().carry());
+    assert_eq!(emulator.register_value(Register::AX), 6);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn files_stay_inside_the_directory() {
+    let directory = directory("sandbox");
+    fs::write(directory.join("in.txt"), "abcdef").unwrap();
+    let dos = Dos::with_io(&directory, io::empty(), io::sink(), io::sink());
+    // mov dx, 0x200 / mov ax, 0x3d00 / int 0x21 / mov si, ax
+    // mov dx, 0x210 / mov ah, 0x41 / int 0x21
+    let mut data = b"../in.txt\0".to_vec();
+    data.resize(0x10, 0);
+    data.extend(b"\\in.txt\0");
+    let emulator = run(
+        dos,
+        &data,
+        &[
+            0xba, 0x00, 0x02, 0xb8, 0x00, 0x3d, 0xcd, 0x21, 0x89, 0xc6, 0xba, 0x10, 0x02, 0xb4,
+            0x41, 0xcd, 0x21,
+        ],
+    );
+    assert_eq!(emulator.register_value(Register::SI), 3);
+    assert!(!emulator.flags().carry());
+    assert!(!directory.join("in.txt").exists());
+    fs::remove_dir_all(directory).unwrap();
+}
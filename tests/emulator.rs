@@ -0,0 +1,831 @@
+//! Instruction level checks of the emulator, on small hand assembled programs.
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use computer_enhance_8086::sim8086::{
+    bus::IoBus,
+    emu::{Emulator, EmulatorError, FlagsRegister, InterruptHook, Termination},
+    opc::mov::Register,
+};
+
+use common::run_on;
+
+fn try_run(program: &[u8]) -> Result<Emulator, EmulatorError> {
+    run_on(Emulator::new(), program)
+}
+
+fn run(program: &[u8]) -> Emulator {
+    try_run(program).unwrap()
+}
+
+#[test]
+fn byte_add_carries_out_of_both_nibble_and_byte() {
+    // mov al, 0xff / add al, 1
+    let emulator = run(&[0xb0, 0xff, 0x04, 0x01]);
+    assert_eq!(emulator.register_value(Register::AX), 0);
+    assert_eq!(emulator.flags().to_string(), "CPAZ");
+}
+
+#[test]
+fn word_sub_overflows_from_most_negative() {
+    // mov ax, 0x8000 / sub ax, 1
+    let emulator = run(&[0xb8, 0x00, 0x80, 0x2d, 0x01, 0x00]);
+    assert_eq!(emulator.register_value(Register::AX), 0x7fff);
+    assert_eq!(emulator.flags().to_string(), "PAO");
+}
+
+#[test]
+fn cmp_borrows_without_storing() {
+    // mov bx, 1 / cmp bx, 2
+    let emulator = run(&[0xbb, 0x01, 0x00, 0x83, 0xfb, 0x02]);
+    assert_eq!(emulator.register_value(Register::BX), 1);
+    assert_eq!(emulator.flags().to_string(), "CPAS");
+}
+
+#[test]
+fn logic_clears_carry_and_overflow() {
+    // mov ax, 0xffff / add ax, 1 / or ax, 0x7f00
+    let emulator = run(&[0xb8, 0xff, 0xff, 0x05, 0x01, 0x00, 0x0d, 0x00, 0x7f]);
+    assert_eq!(emulator.register_value(Register::AX), 0x7f00);
+    assert_eq!(emulator.flags().to_string(), "P");
+}
+
+#[test]
+fn adc_chains_the_carry_of_a_multiword_add() {
+    // dx:ax = 0x0001ffff + 0x7fff8001
+    // mov ax, 0xffff / mov dx, 1 / add ax, 0x8001 / adc dx, 0x7fff
+    let emulator = run(&[
+        0xb8, 0xff, 0xff, 0xba, 0x01, 0x00, 0x05, 0x01, 0x80, 0x81, 0xd2, 0xff, 0x7f,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x8000);
+    assert_eq!(emulator.register_value(Register::DX), 0x8001);
+    // The carry in makes the nibble carry and the signed overflow, nothing carries out.
+    assert_eq!(emulator.flags().to_string(), "ASO");
+
+    // mov dx, 0xffff / stc / adc dx, 0
+    let emulator = run(&[0xba, 0xff, 0xff, 0xf9, 0x83, 0xd2, 0x00]);
+    assert_eq!(emulator.register_value(Register::DX), 0);
+    assert_eq!(emulator.flags().to_string(), "CPAZ");
+}
+
+#[test]
+fn sbb_chains_the_borrow_of_a_multiword_sub() {
+    // dx:ax = 0x00010000 - 1
+    // mov ax, 0 / mov dx, 1 / sub ax, 1 / sbb dx, 0
+    let emulator = run(&[
+        0xb8, 0x00, 0x00, 0xba, 0x01, 0x00, 0x2d, 0x01, 0x00, 0x83, 0xda, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0xffff);
+    assert_eq!(emulator.register_value(Register::DX), 0);
+    assert_eq!(emulator.flags().to_string(), "PZ");
+
+    // mov dx, 0x8000 / stc / sbb dx, 0
+    let emulator = run(&[0xba, 0x00, 0x80, 0xf9, 0x83, 0xda, 0x00]);
+    assert_eq!(emulator.register_value(Register::DX), 0x7fff);
+    assert_eq!(emulator.flags().to_string(), "PAO");
+
+    // mov al, 0 / stc / sbb al, 0xff
+    let emulator = run(&[0xb0, 0x00, 0xf9, 0x1c, 0xff]);
+    assert_eq!(emulator.register_value(Register::AL), 0);
+    assert_eq!(emulator.flags().to_string(), "CPAZ");
+}
+
+#[test]
+fn masking_sets_sign_zero_and_parity_from_the_result() {
+    // AF is undefined after the logical instructions, so only the others are checked.
+    let defined = |emulator: &Emulator| {
+        let flags = emulator.flags();
+        (
+            flags.carry(),
+            flags.overflow(),
+            flags.sign(),
+            flags.zero(),
+            flags.parity(),
+        )
+    };
+
+    // mov al, 0x7f / add al, 1 / and al, 0xf0
+    let emulator = run(&[0xb0, 0x7f, 0x04, 0x01, 0x24, 0xf0]);
+    assert_eq!(emulator.register_value(Register::AL), 0x80);
+    assert_eq!(defined(&emulator), (false, false, true, false, false));
+
+    // mov bl, 3 / stc / or bl, 0x0c
+    let emulator = run(&[0xb3, 0x03, 0xf9, 0x80, 0xcb, 0x0c]);
+    assert_eq!(emulator.register_value(Register::BL), 0x0f);
+    assert_eq!(defined(&emulator), (false, false, false, false, true));
+
+    // mov ax, 0x8000 / sub ax, 1 / stc / xor ax, ax
+    let emulator = run(&[0xb8, 0x00, 0x80, 0x2d, 0x01, 0x00, 0xf9, 0x31, 0xc0]);
+    assert_eq!(emulator.register_value(Register::AX), 0);
+    assert_eq!(defined(&emulator), (false, false, false, true, true));
+
+    // mov cx, 0x8001 / xor cx, 0x0001
+    let emulator = run(&[0xb9, 0x01, 0x80, 0x81, 0xf1, 0x01, 0x00]);
+    assert_eq!(emulator.register_value(Register::CX), 0x8000);
+    assert_eq!(defined(&emulator), (false, false, true, false, true));
+}
+
+#[test]
+fn packed_flags_keep_reserved_bits_set() {
+    // mov al, 0xff / add al, 1
+    let emulator = run(&[0xb0, 0xff, 0x04, 0x01]);
+    let packed = emulator.flags().get();
+    assert_eq!(packed & 0xF002, 0xF002);
+    assert_eq!(
+        packed & !0xF002,
+        FlagsRegister::CARRY
+            | FlagsRegister::PARITY
+            | FlagsRegister::AUXILIARY_CARRY
+            | FlagsRegister::ZERO
+    );
+}
+
+#[test]
+fn byte_moves_keep_high_and_low_halves() {
+    // mov ax, 0x1234 / mov bh, al / mov bl, ah
+    let emulator = run(&[0xb8, 0x34, 0x12, 0x88, 0xc7, 0x88, 0xe3]);
+    assert_eq!(emulator.register_value(Register::BX), 0x3412);
+}
+
+#[test]
+fn byte_arithmetic_on_memory() {
+    // mov bx, 0x10 / mov byte [bx + 1], 0xff / add byte [bx + 1], 2 / mov ax, [bx]
+    let emulator = run(&[
+        0xbb, 0x10, 0x00, 0xc6, 0x47, 0x01, 0xff, 0x80, 0x47, 0x01, 0x02, 0x8b, 0x07,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x0100);
+    assert_eq!(emulator.flags().to_string(), "CA");
+}
+
+#[test]
+fn effective_address_wraps_at_16_bits() {
+    // mov bx, 0xffff / mov si, 2 / mov word [bx + si + 1], 0x1234 / mov ax, [2]
+    let emulator = run(&[
+        0xbb, 0xff, 0xff, 0xbe, 0x02, 0x00, 0xc7, 0x40, 0x01, 0x34, 0x12, 0xa1, 0x02, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x1234);
+}
+
+#[test]
+fn words_are_stored_little_endian_and_wrap_at_the_segment_end() {
+    // mov word [0xffff], 0x1234 / mov byte [2], 0x56 / mov ax, [1]
+    let emulator = run(&[
+        0xc7, 0x06, 0xff, 0xff, 0x34, 0x12, 0xc6, 0x06, 0x02, 0x00, 0x56, 0xa1, 0x01, 0x00,
+    ]);
+    let memory = emulator.memory();
+    assert_eq!(memory.read_u8(0, 0xffff), 0x34);
+    assert_eq!(memory.read_u8(0, 0x0000), 0x12);
+    assert_eq!(memory.read_u16(0, 0xffff), 0x1234);
+    assert_eq!(emulator.register_value(Register::AX), 0x5600);
+}
+
+#[test]
+fn data_segment_translates_addresses() {
+    // mov ax, 0x1000 / mov ds, ax / mov word [4], 0xbeef
+    let emulator = run(&[
+        0xb8, 0x00, 0x10, 0x8e, 0xd8, 0xc7, 0x06, 0x04, 0x00, 0xef, 0xbe,
+    ]);
+    assert_eq!(emulator.register_value(Register::DS), 0x1000);
+    assert_eq!(emulator.memory().read_u16(0x1000, 4), 0xbeef);
+    assert_eq!(emulator.memory().read_u16(0x0fff, 0x14), 0xbeef);
+    assert_eq!(emulator.memory().read_u16(0, 4), 0);
+}
+
+#[test]
+fn bp_based_addresses_default_to_the_stack_segment() {
+    // mov ax, 0x2000 / mov ss, ax / mov bp, 0x10 / mov byte [bp], 7 / mov cx, ss
+    let emulator = run(&[
+        0xb8, 0x00, 0x20, 0x8e, 0xd0, 0xbd, 0x10, 0x00, 0xc6, 0x46, 0x00, 0x07, 0x8c, 0xd1,
+    ]);
+    assert_eq!(emulator.memory().read_u8(0x2000, 0x10), 7);
+    assert_eq!(emulator.memory().read_u8(0, 0x10), 0);
+    assert_eq!(emulator.register_value(Register::CX), 0x2000);
+}
+
+#[test]
+fn segment_override_applies_to_one_instruction() {
+    // mov ax, 0x3000 / mov es, ax / mov word es:[0x10], 0x1234 / mov word [0x10], 0x5678
+    let emulator = run(&[
+        0xb8, 0x00, 0x30, 0x8e, 0xc0, 0x26, 0xc7, 0x06, 0x10, 0x00, 0x34, 0x12, 0xc7, 0x06, 0x10,
+        0x00, 0x78, 0x56,
+    ]);
+    assert_eq!(emulator.memory().read_u16(0x3000, 0x10), 0x1234);
+    assert_eq!(emulator.memory().read_u16(0, 0x10), 0x5678);
+}
+
+#[test]
+fn rep_movsb_copies_forward() {
+    // mov word [0x100], 0x2211 / mov byte [0x102], 0x33 / mov si, 0x100 / mov di, 0x200
+    // mov cx, 3 / cld / rep movsb
+    let emulator = run(&[
+        0xc7, 0x06, 0x00, 0x01, 0x11, 0x22, 0xc6, 0x06, 0x02, 0x01, 0x33, 0xbe, 0x00, 0x01, 0xbf,
+        0x00, 0x02, 0xb9, 0x03, 0x00, 0xfc, 0xf3, 0xa4,
+    ]);
+    assert_eq!(emulator.memory().read_u16(0, 0x200), 0x2211);
+    assert_eq!(emulator.memory().read_u8(0, 0x202), 0x33);
+    assert_eq!(emulator.register_value(Register::CX), 0);
+    assert_eq!(emulator.register_value(Register::SI), 0x103);
+    assert_eq!(emulator.register_value(Register::DI), 0x203);
+}
+
+#[test]
+fn rep_stosw_honors_the_direction_flag() {
+    // mov ax, 0xabcd / mov di, 0x104 / mov cx, 3 / std / rep stosw
+    let emulator = run(&[
+        0xb8, 0xcd, 0xab, 0xbf, 0x04, 0x01, 0xb9, 0x03, 0x00, 0xfd, 0xf3, 0xab,
+    ]);
+    for offset in [0x100, 0x102, 0x104] {
+        assert_eq!(emulator.memory().read_u16(0, offset), 0xabcd);
+    }
+    assert_eq!(emulator.register_value(Register::DI), 0x00fe);
+    assert!(emulator.flags().direction());
+}
+
+#[test]
+fn repe_cmpsb_stops_at_the_first_difference() {
+    // mov word [0x100], 0x2211 / mov word [0x200], 0x3311 / mov si, 0x100 / mov di, 0x200
+    // mov cx, 5 / repe cmpsb
+    let emulator = run(&[
+        0xc7, 0x06, 0x00, 0x01, 0x11, 0x22, 0xc7, 0x06, 0x00, 0x02, 0x11, 0x33, 0xbe, 0x00, 0x01,
+        0xbf, 0x00, 0x02, 0xb9, 0x05, 0x00, 0xf3, 0xa6,
+    ]);
+    assert_eq!(emulator.register_value(Register::CX), 3);
+    assert_eq!(emulator.register_value(Register::SI), 0x102);
+    assert!(!emulator.flags().zero());
+}
+
+#[test]
+fn repne_scasb_finds_the_byte() {
+    // mov byte [0x102], 7 / mov di, 0x100 / mov al, 7 / mov cx, 8 / repne scasb
+    let emulator = run(&[
+        0xc6, 0x06, 0x02, 0x01, 0x07, 0xbf, 0x00, 0x01, 0xb0, 0x07, 0xb9, 0x08, 0x00, 0xf2, 0xae,
+    ]);
+    assert_eq!(emulator.register_value(Register::DI), 0x103);
+    assert_eq!(emulator.register_value(Register::CX), 5);
+    assert!(emulator.flags().zero());
+}
+
+#[test]
+fn lodsw_reads_through_a_segment_override() {
+    // mov ax, 0x4000 / mov es, ax / mov word es:[0x10], 0x1234 / mov si, 0x10 / es: lodsw
+    let emulator = run(&[
+        0xb8, 0x00, 0x40, 0x8e, 0xc0, 0x26, 0xc7, 0x06, 0x10, 0x00, 0x34, 0x12, 0xbe, 0x10, 0x00,
+        0x26, 0xad,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x1234);
+    assert_eq!(emulator.register_value(Register::SI), 0x12);
+}
+
+#[test]
+fn push_and_pop_registers_and_segment_registers() {
+    // mov sp, 0x100 / mov ax, 0x1234 / push ax / mov bx, 0x55 / mov es, bx / push es / pop ds
+    // pop cx
+    let emulator = run(&[
+        0xbc, 0x00, 0x01, 0xb8, 0x34, 0x12, 0x50, 0xbb, 0x55, 0x00, 0x8e, 0xc3, 0x06, 0x1f, 0x59,
+    ]);
+    assert_eq!(emulator.register_value(Register::DS), 0x55);
+    assert_eq!(emulator.register_value(Register::CX), 0x1234);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+    assert_eq!(emulator.memory().read_u16(0, 0xfe), 0x1234);
+}
+
+#[test]
+fn push_and_pop_memory_operands() {
+    // mov word [0x10], 0xbeef / mov sp, 0x100 / push word [0x10] / pop word [0x20]
+    let emulator = run(&[
+        0xc7, 0x06, 0x10, 0x00, 0xef, 0xbe, 0xbc, 0x00, 0x01, 0xff, 0x36, 0x10, 0x00, 0x8f, 0x06,
+        0x20, 0x00,
+    ]);
+    assert_eq!(emulator.memory().read_u16(0, 0x20), 0xbeef);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+}
+
+#[test]
+fn near_call_and_return_popping_arguments() {
+    // 0000 mov sp, 0x100 / jcxz 0x000b
+    // 0005 mov dx, 9 / ret 2
+    // 000b mov ax, 7 / push ax / call 0x0005 / mov bx, 1
+    let emulator = run(&[
+        0xbc, 0x00, 0x01, 0xe3, 0x06, 0xba, 0x09, 0x00, 0xc2, 0x02, 0x00, 0xb8, 0x07, 0x00, 0x50,
+        0xe8, 0xf3, 0xff, 0xbb, 0x01, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::DX), 9);
+    assert_eq!(emulator.register_value(Register::BX), 1);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+    assert_eq!(emulator.memory().read_u16(0, 0xfe), 7);
+    assert_eq!(emulator.memory().read_u16(0, 0xfc), 0x12);
+}
+
+#[test]
+fn far_calls_push_the_segment_too() {
+    // 0000 mov sp, 0x100 / jcxz 0x0009
+    // 0005 mov dx, 9 / retf
+    // 0009 call 0:5 / mov word [0x20], 5 / call far [0x20] / mov bx, 1
+    let emulator = run(&[
+        0xbc, 0x00, 0x01, 0xe3, 0x04, 0xba, 0x09, 0x00, 0xcb, 0x9a, 0x05, 0x00, 0x00, 0x00, 0xc7,
+        0x06, 0x20, 0x00, 0x05, 0x00, 0xff, 0x1e, 0x20, 0x00, 0xbb, 0x01, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::DX), 9);
+    assert_eq!(emulator.register_value(Register::BX), 1);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+    assert_eq!(emulator.memory().read_u16(0, 0xfc), 0x18);
+    assert_eq!(emulator.memory().read_u16(0, 0xfe), 0);
+}
+
+#[test]
+fn pushf_and_popf_move_the_packed_flags() {
+    // mov sp, 0x100 / mov ax, 0xffff / add ax, 1 / pushf / pop bx / mov cx, 0x400 / push cx
+    // popf
+    let emulator = run(&[
+        0xbc, 0x00, 0x01, 0xb8, 0xff, 0xff, 0x05, 0x01, 0x00, 0x9c, 0x5b, 0xb9, 0x00, 0x04, 0x51,
+        0x9d,
+    ]);
+    assert_eq!(emulator.register_value(Register::BX), 0xf057);
+    assert_eq!(emulator.flags().to_string(), "D");
+}
+
+/// Whether the conditional jump `opcode`, after the `setup` instructions, skips the
+/// `mov dl, 1` after it.
+fn jump_taken(setup: &[u8], opcode: u8) -> bool {
+    let mut program = setup.to_vec();
+    program.extend([opcode, 0x02, 0xb2, 0x01]);
+    run(&program).register_value(Register::DL) == 0
+}
+
+#[test]
+fn signed_and_unsigned_jumps_read_different_flags() {
+    const JB: u8 = 0x72;
+    const JBE: u8 = 0x76;
+    const JA: u8 = 0x77;
+    const JL: u8 = 0x7c;
+    const JLE: u8 = 0x7e;
+    const JG: u8 = 0x7f;
+    // mov al, 0x80 / cmp al, 1 ; OF set, SF clear: -128 < 1 but 128 > 1
+    let setup = [0xb0, 0x80, 0x3c, 0x01];
+    assert_eq!(
+        [JL, JG, JB, JA].map(|jump| jump_taken(&setup, jump)),
+        [true, false, false, true]
+    );
+    // mov al, 1 / cmp al, 0xff ; CF set: 1 > -1 but 1 < 255
+    let setup = [0xb0, 0x01, 0x3c, 0xff];
+    assert_eq!(
+        [JL, JG, JB, JA].map(|jump| jump_taken(&setup, jump)),
+        [false, true, true, false]
+    );
+    // mov al, 5 / cmp al, 5
+    let setup = [0xb0, 0x05, 0x3c, 0x05];
+    assert_eq!(
+        [JL, JG, JB, JA, JLE, JBE].map(|jump| jump_taken(&setup, jump)),
+        [false, false, false, false, true, true]
+    );
+}
+
+#[test]
+fn parity_jumps() {
+    const JP: u8 = 0x7a;
+    const JNP: u8 = 0x7b;
+    // mov al, 3 / cmp al, 0 ; two bits set
+    let setup = [0xb0, 0x03, 0x3c, 0x00];
+    assert_eq!(
+        [JP, JNP].map(|jump| jump_taken(&setup, jump)),
+        [true, false]
+    );
+    // mov al, 1 / cmp al, 0 ; one bit set
+    let setup = [0xb0, 0x01, 0x3c, 0x00];
+    assert_eq!(
+        [JP, JNP].map(|jump| jump_taken(&setup, jump)),
+        [false, true]
+    );
+}
+
+#[test]
+fn loop_runs_until_cx_reaches_zero() {
+    // 0000 mov cx, 3
+    // 0003 add ax, 1 / loop 0x0003
+    let emulator = run(&[0xb9, 0x03, 0x00, 0x05, 0x01, 0x00, 0xe2, 0xfb]);
+    assert_eq!(emulator.register_value(Register::AX), 3);
+    assert_eq!(emulator.register_value(Register::CX), 0);
+}
+
+#[test]
+fn loopz_and_loopnz_also_stop_on_zf() {
+    // 0000 mov cx, 5
+    // 0003 inc ax / cmp ax, 2 / loopz 0x0003
+    let emulator = run(&[0xb9, 0x05, 0x00, 0x40, 0x3d, 0x02, 0x00, 0xe1, 0xfa]);
+    assert_eq!(emulator.register_value(Register::AX), 1);
+    assert_eq!(emulator.register_value(Register::CX), 4);
+    // 0000 mov cx, 5
+    // 0003 inc ax / cmp bx, bx / loopz 0x0003
+    let emulator = run(&[0xb9, 0x05, 0x00, 0x40, 0x39, 0xdb, 0xe1, 0xfb]);
+    assert_eq!(emulator.register_value(Register::AX), 5);
+    assert_eq!(emulator.register_value(Register::CX), 0);
+    // 0000 mov cx, 5
+    // 0003 inc ax / cmp ax, 2 / loopnz 0x0003
+    let emulator = run(&[0xb9, 0x05, 0x00, 0x40, 0x3d, 0x02, 0x00, 0xe0, 0xfa]);
+    assert_eq!(emulator.register_value(Register::AX), 2);
+    assert_eq!(emulator.register_value(Register::CX), 3);
+}
+
+#[test]
+fn jcxz_only_jumps_on_zero_cx() {
+    const JCXZ: u8 = 0xe3;
+    // mov cx, 0 / cmp al, 1 ; ZF clear, CX zero
+    assert!(jump_taken(&[0xb9, 0x00, 0x00, 0x3c, 0x01], JCXZ));
+    // mov cx, 1 / cmp al, 0 ; ZF set, CX not zero
+    assert!(!jump_taken(&[0xb9, 0x01, 0x00, 0x3c, 0x00], JCXZ));
+}
+
+#[test]
+fn jumps_of_every_kind() {
+    // 0000 jmp short 0x0005 / mov ax, 1
+    // 0005 jmp near 0x000b / mov ax, 2
+    // 000b mov bx, 0x0013 / jmp bx / mov ax, 3
+    // 0013 mov word [0x20], 0x0020 / jmp far [0x20] / mov ax, 4
+    // 0020 jmp 0:0x0028 / mov ax, 5
+    // 0028 mov cx, 6
+    let emulator = run(&[
+        0xeb, 0x03, 0xb8, 0x01, 0x00, 0xe9, 0x03, 0x00, 0xb8, 0x02, 0x00, 0xbb, 0x13, 0x00, 0xff,
+        0xe3, 0xb8, 0x03, 0x00, 0xc7, 0x06, 0x20, 0x00, 0x20, 0x00, 0xff, 0x2e, 0x20, 0x00, 0xb8,
+        0x04, 0x00, 0xea, 0x28, 0x00, 0x00, 0x00, 0xb8, 0x05, 0x00, 0xb9, 0x06, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0);
+    assert_eq!(emulator.register_value(Register::CX), 6);
+    assert_eq!(emulator.register_value(Register::CS), 0);
+}
+
+#[test]
+fn unsigned_byte_multiply_into_ax() {
+    // mov al, 200 / mov bl, 3 / mul bl
+    let emulator = run(&[0xb0, 0xc8, 0xb3, 0x03, 0xf6, 0xe3]);
+    assert_eq!(emulator.register_value(Register::AX), 600);
+    assert!(emulator.flags().carry() && emulator.flags().overflow());
+}
+
+#[test]
+fn signed_word_multiply_into_dx_ax() {
+    // mov ax, -2 / mov bx, 3 / imul bx
+    let emulator = run(&[0xb8, 0xfe, 0xff, 0xbb, 0x03, 0x00, 0xf7, 0xeb]);
+    assert_eq!(emulator.register_value(Register::AX), 0xfffa);
+    assert_eq!(emulator.register_value(Register::DX), 0xffff);
+    assert!(!emulator.flags().carry() && !emulator.flags().overflow());
+}
+
+#[test]
+fn unsigned_word_divide_of_dx_ax() {
+    // mov dx, 1 / mov ax, 0 / mov cx, 3 / div cx
+    let emulator = run(&[
+        0xba, 0x01, 0x00, 0xb8, 0x00, 0x00, 0xb9, 0x03, 0x00, 0xf7, 0xf1,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x5555);
+    assert_eq!(emulator.register_value(Register::DX), 1);
+}
+
+#[test]
+fn signed_byte_divide_truncates_toward_zero() {
+    // mov ax, -7 / mov bl, 2 / idiv bl
+    let emulator = run(&[0xb8, 0xf9, 0xff, 0xb3, 0x02, 0xf6, 0xfb]);
+    assert_eq!(emulator.register_value(Register::AL), 0xfd);
+    assert_eq!(emulator.register_value(Register::AH), 0xff);
+}
+
+#[test]
+fn divide_by_zero_without_a_handler_is_an_error() {
+    // mov ax, 1 / mov bl, 0 / div bl
+    let result = try_run(&[0xb8, 0x01, 0x00, 0xb3, 0x00, 0xf6, 0xf3]);
+    assert_eq!(
+        result.unwrap_err(),
+        EmulatorError::DivideError { cs: 0, ip: 5 }
+    );
+}
+
+#[test]
+fn divide_overflow_raises_interrupt_0() {
+    // 0000 mov word [0], 0x0010 / mov sp, 0x100 / mov ax, 0x1000 / mov bl, 2 / div bl
+    // 0010 mov cx, 9
+    let emulator = run(&[
+        0xc7, 0x06, 0x00, 0x00, 0x10, 0x00, 0xbc, 0x00, 0x01, 0xb8, 0x00, 0x10, 0xb3, 0x02, 0xf6,
+        0xf3, 0xb9, 0x09, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::AX), 0x1000);
+    assert_eq!(emulator.register_value(Register::CX), 9);
+    assert_eq!(emulator.register_value(Register::SP), 0xfa);
+    assert_eq!(emulator.memory().read_u16(0, 0xfa), 0x10);
+    assert_eq!(emulator.memory().read_u16(0, 0xfc), 0);
+    assert_eq!(emulator.memory().read_u16(0, 0xfe), 0xf002);
+}
+
+#[test]
+fn shifts_set_carry_overflow_and_result_flags() {
+    // mov al, 0x81 / shl al, 1
+    let emulator = run(&[0xb0, 0x81, 0xd0, 0xe0]);
+    assert_eq!(emulator.register_value(Register::AL), 0x02);
+    assert_eq!(emulator.flags().to_string(), "CO");
+
+    // mov bx, 0x8001 / shr bx, 1
+    let emulator = run(&[0xbb, 0x01, 0x80, 0xd1, 0xeb]);
+    assert_eq!(emulator.register_value(Register::BX), 0x4000);
+    assert_eq!(emulator.flags().to_string(), "CPO");
+
+    // mov ax, 0x8004 / mov cl, 2 / sar ax, cl
+    let emulator = run(&[0xb8, 0x04, 0x80, 0xb1, 0x02, 0xd3, 0xf8]);
+    assert_eq!(emulator.register_value(Register::AX), 0xe001);
+    assert_eq!(emulator.flags().to_string(), "S");
+}
+
+#[test]
+fn rotates_only_touch_carry_and_overflow() {
+    // mov dl, 0x81 / rol dl, 1
+    let emulator = run(&[0xb2, 0x81, 0xd0, 0xc2]);
+    assert_eq!(emulator.register_value(Register::DL), 0x03);
+    assert_eq!(emulator.flags().to_string(), "CO");
+
+    // stc / mov dh, 1 / rcr dh, 1
+    let emulator = run(&[0xf9, 0xb6, 0x01, 0xd0, 0xde]);
+    assert_eq!(emulator.register_value(Register::DH), 0x80);
+    assert_eq!(emulator.flags().to_string(), "CO");
+
+    // mov ax, 0x1234 / clc / mov cl, 17 / rcl ax, cl
+    let emulator = run(&[0xb8, 0x34, 0x12, 0xf8, 0xb1, 0x11, 0xd3, 0xd0]);
+    assert_eq!(emulator.register_value(Register::AX), 0x1234);
+    assert!(!emulator.flags().carry());
+}
+
+#[test]
+fn shift_by_zero_changes_nothing() {
+    // mov ax, 0x8000 / stc / mov cl, 0 / shl ax, cl
+    let emulator = run(&[0xb8, 0x00, 0x80, 0xf9, 0xb1, 0x00, 0xd3, 0xe0]);
+    assert_eq!(emulator.register_value(Register::AX), 0x8000);
+    assert_eq!(emulator.flags().to_string(), "C");
+}
+
+#[test]
+fn inc_and_dec_preserve_carry() {
+    // stc / mov al, 0xff / inc al
+    let emulator = run(&[0xf9, 0xb0, 0xff, 0xfe, 0xc0]);
+    assert_eq!(emulator.register_value(Register::AL), 0);
+    assert_eq!(emulator.flags().to_string(), "CPAZ");
+
+    // inc cx / mov word [0x10], 0x8000 / dec word [0x10]
+    let emulator = run(&[
+        0x41, 0xc7, 0x06, 0x10, 0x00, 0x00, 0x80, 0xff, 0x0e, 0x10, 0x00,
+    ]);
+    assert_eq!(emulator.memory().read_u16(0, 0x10), 0x7fff);
+    assert_eq!(emulator.register_value(Register::CX), 1);
+    assert_eq!(emulator.flags().to_string(), "PAO");
+}
+
+#[test]
+fn neg_borrows_unless_zero() {
+    // mov al, 0x80 / neg al
+    let emulator = run(&[0xb0, 0x80, 0xf6, 0xd8]);
+    assert_eq!(emulator.register_value(Register::AL), 0x80);
+    assert_eq!(emulator.flags().to_string(), "CSO");
+
+    // stc / mov bx, 0 / neg bx
+    let emulator = run(&[0xf9, 0xbb, 0x00, 0x00, 0xf7, 0xdb]);
+    assert_eq!(emulator.flags().to_string(), "PZ");
+}
+
+#[test]
+fn not_and_test_leave_what_they_should_alone() {
+    // stc / mov bx, 0x0ff0 / not bx
+    let emulator = run(&[0xf9, 0xbb, 0xf0, 0x0f, 0xf7, 0xd3]);
+    assert_eq!(emulator.register_value(Register::BX), 0xf00f);
+    assert_eq!(emulator.flags().to_string(), "C");
+
+    // stc / mov al, 0xf0 / test al, 0x0f
+    let emulator = run(&[0xf9, 0xb0, 0xf0, 0xa8, 0x0f]);
+    assert_eq!(emulator.register_value(Register::AL), 0xf0);
+    assert_eq!(emulator.flags().to_string(), "PZ");
+}
+
+#[test]
+fn daa_adjusts_both_digits_after_add() {
+    // mov al, 0x79 / add al, 0x35 / daa
+    let emulator = run(&[0xb0, 0x79, 0x04, 0x35, 0x27]);
+    assert_eq!(emulator.register_value(Register::AL), 0x14);
+    // OF is left as the ADD set it, the 8086 leaves it undefined.
+    assert_eq!(emulator.flags().to_string(), "CPAO");
+
+    // mov al, 0x99 / add al, 1 / daa
+    let emulator = run(&[0xb0, 0x99, 0x04, 0x01, 0x27]);
+    assert_eq!(emulator.register_value(Register::AL), 0x00);
+    assert_eq!(emulator.flags().to_string(), "CPAZ");
+}
+
+#[test]
+fn das_adjusts_after_sub_and_keeps_the_borrow() {
+    // mov al, 0x10 / sub al, 1 / das
+    let emulator = run(&[0xb0, 0x10, 0x2c, 0x01, 0x2f]);
+    assert_eq!(emulator.register_value(Register::AL), 0x09);
+    assert_eq!(emulator.flags().to_string(), "PA");
+
+    // mov al, 0x12 / sub al, 0x25 / das
+    let emulator = run(&[0xb0, 0x12, 0x2c, 0x25, 0x2f]);
+    assert_eq!(emulator.register_value(Register::AL), 0x87);
+    assert_eq!(emulator.flags().to_string(), "CPAS");
+}
+
+#[test]
+fn aaa_and_aas_carry_into_ah() {
+    // mov ax, 8 / add al, 9 / aaa
+    let emulator = run(&[0xb8, 0x08, 0x00, 0x04, 0x09, 0x37]);
+    assert_eq!(emulator.register_value(Register::AX), 0x0107);
+    assert!(emulator.flags().carry() && emulator.flags().auxiliary_carry());
+
+    // mov ax, 2 / sub al, 5 / aas
+    let emulator = run(&[0xb8, 0x02, 0x00, 0x2c, 0x05, 0x3f]);
+    assert_eq!(emulator.register_value(Register::AX), 0xff07);
+    assert!(emulator.flags().carry() && emulator.flags().auxiliary_carry());
+
+    // mov ax, 0x0104 / add al, 3 / aaa
+    let emulator = run(&[0xb8, 0x04, 0x01, 0x04, 0x03, 0x37]);
+    assert_eq!(emulator.register_value(Register::AX), 0x0107);
+    assert!(!emulator.flags().carry() && !emulator.flags().auxiliary_carry());
+}
+
+#[test]
+fn aam_and_aad_convert_between_binary_and_digits() {
+    // mov al, 63 / aam
+    let emulator = run(&[0xb0, 0x3f, 0xd4, 0x0a]);
+    assert_eq!(emulator.register_value(Register::AX), 0x0603);
+
+    // mov ax, 0x0603 / aad
+    let emulator = run(&[0xb8, 0x03, 0x06, 0xd5, 0x0a]);
+    assert_eq!(emulator.register_value(Register::AX), 0x003f);
+    assert_eq!(emulator.flags().to_string(), "P");
+}
+
+#[test]
+fn xchg_swaps_registers_and_memory() {
+    // mov ax, 1 / mov bx, 2 / xchg ax, bx / xchg al, ah
+    let emulator = run(&[0xb8, 0x01, 0x00, 0xbb, 0x02, 0x00, 0x93, 0x86, 0xc4]);
+    assert_eq!(emulator.register_value(Register::AX), 0x0200);
+    assert_eq!(emulator.register_value(Register::BX), 1);
+
+    // mov word [0x10], 5 / mov cx, 7 / mov bx, 0x10 / xchg cx, [bx]
+    let emulator = run(&[
+        0xc7, 0x06, 0x10, 0x00, 0x05, 0x00, 0xb9, 0x07, 0x00, 0xbb, 0x10, 0x00, 0x87, 0x0f,
+    ]);
+    assert_eq!(emulator.register_value(Register::CX), 5);
+    assert_eq!(emulator.memory().read_u16(0, 0x10), 7);
+}
+
+#[test]
+fn xlat_looks_up_al_in_the_table_at_bx() {
+    // mov byte [0x105], 0x42 / mov bx, 0x100 / mov al, 5 / xlatb
+    let emulator = run(&[
+        0xc6, 0x06, 0x05, 0x01, 0x42, 0xbb, 0x00, 0x01, 0xb0, 0x05, 0xd7,
+    ]);
+    assert_eq!(emulator.register_value(Register::AL), 0x42);
+}
+
+#[test]
+fn lea_computes_the_offset_and_lds_les_load_far_pointers() {
+    // mov bx, 0x10 / mov si, 0x20 / lea ax, [bx + si + 4]
+    let emulator = run(&[0xbb, 0x10, 0x00, 0xbe, 0x20, 0x00, 0x8d, 0x40, 0x04]);
+    assert_eq!(emulator.register_value(Register::AX), 0x34);
+
+    // mov word [0x10], 0x1234 / mov word [0x12], 0x5678 / mov bx, 0x10 / les di, [bx]
+    // lds bx, [0x10]
+    let emulator = run(&[
+        0xc7, 0x06, 0x10, 0x00, 0x34, 0x12, 0xc7, 0x06, 0x12, 0x00, 0x78, 0x56, 0xbb, 0x10, 0x00,
+        0xc4, 0x3f, 0xc5, 0x1e, 0x10, 0x00,
+    ]);
+    assert_eq!(emulator.register_value(Register::DI), 0x1234);
+    assert_eq!(emulator.register_value(Register::ES), 0x5678);
+    assert_eq!(emulator.register_value(Register::BX), 0x1234);
+    assert_eq!(emulator.register_value(Register::DS), 0x5678);
+}
+
+#[test]
+fn lahf_and_sahf_move_the_low_flags_through_ah() {
+    // stc / lahf
+    let emulator = run(&[0xf9, 0x9f]);
+    assert_eq!(emulator.register_value(Register::AH), 0x03);
+
+    // std / mov ah, 0xd5 / sahf
+    let emulator = run(&[0xfd, 0xb4, 0xd5, 0x9e]);
+    assert_eq!(emulator.flags().to_string(), "CPAZSD");
+}
+
+#[test]
+fn cbw_and_cwd_sign_extend() {
+    // mov al, 0x80 / cbw / cwd
+    let emulator = run(&[0xb0, 0x80, 0x98, 0x99]);
+    assert_eq!(emulator.register_value(Register::AX), 0xff80);
+    assert_eq!(emulator.register_value(Register::DX), 0xffff);
+
+    // mov ax, 0x7f00 / mov dx, 5 / cwd
+    let emulator = run(&[0xb8, 0x00, 0x7f, 0xba, 0x05, 0x00, 0x99]);
+    assert_eq!(emulator.register_value(Register::DX), 0);
+}
+
+#[test]
+fn int_calls_the_handler_in_the_vector_table_and_iret_returns() {
+    // 0000 mov word [0x84], 0x0011 / mov sp, 0x100 / sti / int 0x21 / mov dx, 1
+    // 000f jmp short 0x0017
+    // 0011 mov cx, 9 / pushf / pop bx / iret
+    let emulator = run(&[
+        0xc7, 0x06, 0x84, 0x00, 0x11, 0x00, 0xbc, 0x00, 0x01, 0xfb, 0xcd, 0x21, 0xba, 0x01, 0x00,
+        0xeb, 0x06, 0xb9, 0x09, 0x00, 0x9c, 0x5b, 0xcf,
+    ]);
+    assert_eq!(emulator.register_value(Register::CX), 9);
+    assert_eq!(emulator.register_value(Register::DX), 1);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+    assert_eq!(
+        emulator.register_value(Register::BX) & FlagsRegister::INTERRUPT,
+        0
+    );
+    assert!(emulator.flags().interrupt());
+}
+
+/// Handles the vectors below 0x20, keeping track of every vector it sees.
+struct Recorder(Rc<RefCell<Vec<u8>>>);
+
+impl InterruptHook for Recorder {
+    fn interrupt(&mut self, emulator: &mut Emulator, vector: u8) -> bool {
+        self.0.borrow_mut().push(vector);
+        if vector >= 0x20 {
+            return false;
+        }
+        emulator.set_register_value(Register::DL, vector as u16);
+        true
+    }
+}
+
+#[test]
+fn the_host_hook_comes_before_the_vector_table() {
+    let vectors = Rc::new(RefCell::new(Vec::new()));
+    let mut emulator = Emulator::new();
+    emulator.set_interrupt_hook(Recorder(vectors.clone()));
+    // 0000 mov sp, 0x100 / int3 / int 0x10 / into / mov al, 0x7f / add al, 1 / into
+    // 000c mov word [0x80], 0x0014 / int 0x20
+    // 0014 mov cx, 9
+    let emulator = run_on(
+        emulator,
+        &[
+            0xbc, 0x00, 0x01, 0xcc, 0xcd, 0x10, 0xce, 0xb0, 0x7f, 0x04, 0x01, 0xce, 0xc7, 0x06,
+            0x80, 0x00, 0x14, 0x00, 0xcd, 0x20, 0xb9, 0x09, 0x00,
+        ],
+    )
+    .unwrap();
+    assert_eq!(*vectors.borrow(), [3, 0x10, 4, 0x20]);
+    assert_eq!(emulator.register_value(Register::DL), 4);
+    assert_eq!(emulator.register_value(Register::CX), 9);
+    assert_eq!(emulator.register_value(Register::SP), 0xfa);
+}
+
+#[test]
+fn hlt_stops_the_program() {
+    // mov cx, 1 / hlt / mov cx, 2
+    let emulator = run(&[0xb9, 0x01, 0x00, 0xf4, 0xb9, 0x02, 0x00]);
+    assert_eq!(emulator.termination(), Some(Termination::Halted));
+    assert_eq!(emulator.register_value(Register::CX), 1);
+    assert_eq!(emulator.instruction_pointer, 4);
+}
+
+#[test]
+fn esc_is_a_no_op_without_a_coprocessor() {
+    // esc 0, [bx] / esc 63, [bp + si + 2] / mov cx, 1
+    let emulator = run(&[0xd8, 0x07, 0xdf, 0x7a, 0x02, 0xb9, 0x01, 0x00]);
+    assert_eq!(emulator.register_value(Register::CX), 1);
+    assert_eq!(emulator.instruction_pointer, 8);
+    assert_eq!(emulator.memory().non_zero().count(), 0);
+}
+
+/// Ports reading back their own low byte, the writes kept for the test.
+struct Ports(Rc<RefCell<Vec<(u16, u8)>>>);
+
+impl IoBus for Ports {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        port as u8
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        self.0.borrow_mut().push((port, value));
+    }
+}
+
+#[test]
+fn in_and_out_go_through_the_io_bus() {
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let mut emulator = Emulator::new();
+    emulator.set_io_bus(Ports(writes.clone()));
+    // in al, 0x40 / mov bl, al / mov dx, 0x3f8 / in ax, dx / out 0x60, al / out dx, ax
+    let emulator = run_on(
+        emulator,
+        &[
+            0xe4, 0x40, 0x88, 0xc3, 0xba, 0xf8, 0x03, 0xed, 0xe6, 0x60, 0xef,
+        ],
+    )
+    .unwrap();
+    assert_eq!(emulator.register_value(Register::BL), 0x40);
+    assert_eq!(emulator.register_value(Register::AX), 0xf9f8);
+    assert_eq!(
+        *writes.borrow(),
+        [(0x60, 0xf8), (0x3f8, 0xf8), (0x3f9, 0xf9)]
+    );
+}
@@ -0,0 +1,128 @@
+//! Regression suite over the `test/listing_*` binaries. Each listing is checked against
+//! `listing_*.asm`, the expected disassembly, and, when there is one, `listing_*.state`,
+//! the expected state of the emulator after running it.
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use computer_enhance_8086::sim8086::{dis::Dissassembler, emu::Emulator};
+
+use common::run_on;
+
+fn listings() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test");
+    let mut listings: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.starts_with("listing_") && path.extension().is_none()
+        })
+        .collect();
+    listings.sort();
+    listings
+}
+
+/// The listing as the `disasm` mode prints it, with a decode error as a trailing comment.
+fn disassemble(program: &[u8]) -> String {
+    match Dissassembler::new(program).listing(false) {
+        (listing, Some(err)) => format!("{}; {}\n", listing, err),
+        (listing, None) => listing,
+    }
+}
+
+fn emulate(program: &[u8]) -> String {
+    run_on(Emulator::new(), program).unwrap().to_string()
+}
+
+/// Line by line diff, `-` for expected and `+` for actual lines that differ.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut diff = String::new();
+    for line in 0..expected.len().max(actual.len()) {
+        match (expected.get(line), actual.get(line)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                diff += &format!("  {}\n", expected)
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    diff += &format!("- {}\n", expected);
+                }
+                if let Some(actual) = actual {
+                    diff += &format!("+ {}\n", actual);
+                }
+            }
+        }
+    }
+    diff
+}
+
+/// Compares every listing that has an expectation next to it, reporting all mismatches.
+fn check_all(extension: &str, required: bool, produce: impl Fn(&[u8]) -> String) {
+    let mut failures = Vec::new();
+    for listing in listings() {
+        let expected_path = listing.with_extension(extension);
+        let Ok(expected) = fs::read_to_string(&expected_path) else {
+            if required {
+                failures.push(format!("{} is missing", expected_path.display()));
+            }
+            continue;
+        };
+        let actual = produce(&fs::read(&listing).unwrap());
+        if actual != expected {
+            failures.push(format!(
+                "{} does not match:\n{}",
+                expected_path.display(),
+                diff(&expected, &actual)
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn disassembly_matches_expected_listings() {
+    check_all("asm", true, disassemble);
+}
+
+#[test]
+fn emulation_matches_expected_state() {
+    check_all("state", false, emulate);
+}
+
+/// Assembles the expected listings back with NASM and compares them with the binaries, skipped
+/// when NASM is not installed.
+#[test]
+fn expected_listings_reassemble_to_the_binaries() {
+    if Command::new("nasm").arg("-v").output().is_err() {
+        eprintln!("nasm not found, skipping the round trip");
+        return;
+    }
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    for listing in listings() {
+        let name = listing.file_name().unwrap().to_str().unwrap();
+        let output = out_dir.join(name);
+        let status = Command::new("nasm")
+            .args(["-f", "bin", "-o"])
+            .arg(&output)
+            .arg(listing.with_extension("asm"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "nasm failed on {}.asm", name);
+        let original = fs::read(&listing).unwrap();
+        let reassembled = fs::read(&output).unwrap();
+        // A listing that ends in a decode error leaves the undecodable tail out.
+        assert_eq!(
+            Some(&reassembled[..]),
+            original.get(..reassembled.len()),
+            "{} does not reassemble to the same bytes",
+            name
+        );
+    }
+}
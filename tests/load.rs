@@ -0,0 +1,208 @@
+//! Programs loaded into memory the way DOS does it, then run from there.
+
+mod common;
+
+use std::{env, io};
+
+use computer_enhance_8086::sim8086::{
+    dos::Dos,
+    emu::{Emulator, Termination},
+    load::{load, load_com, load_exe, LoadError},
+    opc::mov::Register,
+};
+
+use common::{run_loaded, Output};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Runs a program loaded at 0x1000 until it terminates, returning what it printed.
+fn run_program(file: &[u8], args: &[String]) -> (Emulator, String) {
+    let output = Output::default();
+    let mut emulator = Emulator::new();
+    emulator.set_interrupt_hook(Dos::with_io(
+        env::temp_dir(),
+        io::empty(),
+        output.clone(),
+        io::sink(),
+    ));
+    load(&mut emulator, 0x1000, file, args).unwrap();
+    (run_loaded(emulator).unwrap(), output.text())
+}
+
+#[test]
+fn com_programs_get_a_psp_and_one_segment() {
+    let mut emulator = Emulator::new();
+    load_com(&mut emulator, 0x1000, &[0x90, 0xc3], &args(&["one", "two"])).unwrap();
+    for register in [Register::CS, Register::DS, Register::ES, Register::SS] {
+        assert_eq!(emulator.register_value(register), 0x1000);
+    }
+    assert_eq!(emulator.register_value(Register::SP), 0xfffe);
+    assert_eq!(emulator.instruction_pointer, 0x100);
+    let memory = emulator.memory();
+    assert_eq!(memory.read_u16(0x1000, 0), 0x20cd);
+    assert_eq!(memory.read_u16(0x1000, 0x100), 0xc390);
+    assert_eq!(memory.read_u16(0x1000, 0xfffe), 0);
+    let tail: Vec<u8> = (0x80..0x8a)
+        .map(|offset| memory.read_u8(0x1000, offset))
+        .collect();
+    assert_eq!(tail, b"\x08 one two\r");
+}
+
+#[test]
+fn com_programs_read_their_command_tail() {
+    // mov cl, [0x80] / mov ch, 0 / mov dx, 0x81 / mov bx, 1 / mov ah, 0x40 / int 0x21
+    // mov al, [0x80] / mov ah, 0x4c / int 0x21
+    let (emulator, printed) = run_program(
+        &[
+            0x8a, 0x0e, 0x80, 0x00, 0xb5, 0x00, 0xba, 0x81, 0x00, 0xbb, 0x01, 0x00, 0xb4, 0x40,
+            0xcd, 0x21, 0xa0, 0x80, 0x00, 0xb4, 0x4c, 0xcd, 0x21,
+        ],
+        &args(&["one", "two"]),
+    );
+    assert_eq!(printed, " one two");
+    assert_eq!(
+        emulator.termination(),
+        Some(Termination::Exited { code: 8 })
+    );
+}
+
+#[test]
+fn ret_from_a_com_program_goes_to_int_20h() {
+    // mov dx, 0x10b / mov ah, 9 / int 0x21 / mov al, 5 / ret / nop / db "Hi there$"
+    let mut image = vec![
+        0xba, 0x0b, 0x01, 0xb4, 0x09, 0xcd, 0x21, 0xb0, 0x05, 0xc3, 0x90,
+    ];
+    image.extend(b"Hi there$");
+    let (emulator, printed) = run_program(&image, &[]);
+    assert_eq!(printed, "Hi there");
+    assert_eq!(
+        emulator.termination(),
+        Some(Termination::Exited { code: 0 })
+    );
+}
+
+#[test]
+fn com_loading_errors() {
+    let mut emulator = Emulator::new();
+    assert_eq!(
+        load_com(&mut emulator, 0x1000, &vec![0; 0xff00], &[]),
+        Err(LoadError::ImageTooLarge {
+            size: 0xff00,
+            limit: 0xfefe
+        })
+    );
+    assert_eq!(
+        load_com(&mut emulator, 0x1000, &[0xc3], &args(&[&"x".repeat(126)])),
+        Err(LoadError::CommandTailTooLong { length: 127 })
+    );
+}
+
+/// An .EXE with its data in a segment of its own: a 2 paragraph header with one relocation,
+/// then the code paragraph and the data paragraph, and 16 more paragraphs for the stack.
+fn exe() -> Vec<u8> {
+    let mut file = vec![0; 0x40];
+    for (offset, value) in [
+        (0x00, 0x5a4d), // signature
+        (0x02, 0x40),   // bytes in the last page
+        (0x04, 1),      // pages
+        (0x06, 1),      // relocations
+        (0x08, 2),      // header paragraphs
+        (0x0a, 0x10),   // minimum allocation
+        (0x0c, 0xffff), // maximum allocation
+        (0x0e, 2),      // ss
+        (0x10, 0x100),  // sp
+        (0x14, 0),      // ip
+        (0x16, 0),      // cs
+        (0x18, 0x1c),   // relocation table
+        (0x1c, 1),      // relocation offset
+        (0x1e, 0),      // relocation segment
+    ] {
+        file[offset..offset + 2].copy_from_slice(&u16::to_le_bytes(value));
+    }
+    // mov ax, 1 ; the data segment, relocated / mov ds, ax / xor dx, dx / mov ah, 9 / int 0x21
+    // mov ax, 0x4c00 / int 0x21
+    file[0x20..0x30].copy_from_slice(&[
+        0xb8, 0x01, 0x00, 0x8e, 0xd8, 0x31, 0xd2, 0xb4, 0x09, 0xcd, 0x21, 0xb8, 0x00, 0x4c, 0xcd,
+        0x21,
+    ]);
+    file[0x30..0x37].copy_from_slice(b"MZ exe$");
+    file
+}
+
+#[test]
+fn exe_programs_are_relocated_after_the_psp() {
+    let mut emulator = Emulator::new();
+    load_exe(&mut emulator, 0x1000, &exe(), &args(&["x"])).unwrap();
+    assert_eq!(emulator.register_value(Register::CS), 0x1010);
+    assert_eq!(emulator.register_value(Register::SS), 0x1012);
+    assert_eq!(emulator.register_value(Register::SP), 0x100);
+    assert_eq!(emulator.register_value(Register::DS), 0x1000);
+    assert_eq!(emulator.register_value(Register::ES), 0x1000);
+    assert_eq!(emulator.instruction_pointer, 0);
+    let memory = emulator.memory();
+    assert_eq!(memory.read_u16(0x1010, 1), 0x1011);
+    // All the memory up to 640 KiB, the maximum allocation being more than there is.
+    assert_eq!(memory.read_u16(0x1000, 2), 0xa000);
+    assert_eq!(memory.read_u16(0x1000, 0x80), 0x2002);
+}
+
+#[test]
+fn exe_programs_run_with_their_own_segments() {
+    let (emulator, printed) = run_program(&exe(), &[]);
+    assert_eq!(printed, "MZ exe");
+    assert_eq!(emulator.register_value(Register::DS), 0x1011);
+    assert_eq!(
+        emulator.termination(),
+        Some(Termination::Exited { code: 0 })
+    );
+}
+
+#[test]
+fn exe_images_can_be_larger_than_a_segment() {
+    let mut file = exe();
+    file.resize(0x20 + 0x12000, 0);
+    file[0x20 + 0x10000] = 0xaa;
+    // 0x91 pages, 0x20 bytes in the last one.
+    file[0x02..0x06].copy_from_slice(&[0x20, 0x00, 0x91, 0x00]);
+    let mut emulator = Emulator::new();
+    load_exe(&mut emulator, 0x1000, &file, &[]).unwrap();
+    let memory = emulator.memory();
+    assert_eq!(memory.read_u8(0x2010, 0), 0xaa);
+    assert_eq!(memory.read_u8(0x1010, 0), 0xb8);
+}
+
+#[test]
+fn exe_loading_errors() {
+    let mut emulator = Emulator::new();
+    let mut file = exe();
+    file[0] = b'N';
+    assert_eq!(
+        load_exe(&mut emulator, 0x1000, &file, &[]),
+        Err(LoadError::BadSignature { found: 0x5a4e })
+    );
+    assert_eq!(
+        load_exe(&mut emulator, 0x1000, &exe()[..0x38], &[]),
+        Err(LoadError::Truncated {
+            size: 0x38,
+            expected: 0x40
+        })
+    );
+    assert_eq!(
+        load_exe(&mut emulator, 0x1000, &exe()[..0x10], &[]),
+        Err(LoadError::Truncated {
+            size: 0x10,
+            expected: 0x1c
+        })
+    );
+    let mut file = exe();
+    file[0x0a..0x0c].copy_from_slice(&[0xff, 0xff]);
+    assert_eq!(
+        load_exe(&mut emulator, 0x1000, &file, &[]),
+        Err(LoadError::InsufficientMemory {
+            needed: 0x10001,
+            available: 0x8ff0
+        })
+    );
+}